@@ -0,0 +1,261 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use crate::cycle::{Cycle, CycleDetector};
+use crate::matrix::{Coord, Matrix, SparseGrid};
+
+pub trait Backend: Clone + PartialEq {
+    /// Empties `self` so it can receive the generation following `current`.
+    fn reset_like(&mut self, current: &Self);
+
+    /// Canonical form of the state; equal keys mean equal states.
+    type Key: Hash + Eq;
+
+    fn key(&self) -> Self::Key;
+}
+
+impl<T: Clone + Default + Hash + Eq> Backend for Matrix<T> {
+    fn reset_like(&mut self, current: &Self) {
+        self.width = current.width;
+        self.height = current.height;
+        self.data.clear();
+        self.data.resize_with(current.size(), T::default);
+    }
+
+    type Key = Self;
+
+    fn key(&self) -> Self::Key {
+        self.clone()
+    }
+}
+
+impl<T: Clone + Hash + Eq> Backend for SparseGrid<T> {
+    fn reset_like(&mut self, _current: &Self) {
+        self.clear();
+    }
+
+    type Key = Vec<(Coord, T)>;
+
+    fn key(&self) -> Self::Key {
+        // Cells are unordered, so list them sorted by position.
+        let mut cells = self
+            .iter()
+            .map(|(value, coord)| (coord, value.clone()))
+            .collect::<Vec<_>>();
+        cells.sort_unstable_by_key(|&(coord, _)| coord);
+        cells
+    }
+}
+
+pub trait Rule<B> {
+    fn step(&self, current: &B, next: &mut B, generation: usize);
+}
+
+/// Game-of-Life style rule: the next value of a cell depends only on its neighbourhood.
+#[allow(dead_code)]
+pub trait CellRule<T> {
+    fn offsets(&self) -> &[(i32, i32)];
+
+    fn next(&self, cell: Option<&T>, neighbours: &[&T]) -> Option<T>;
+}
+
+#[allow(dead_code)]
+pub struct LocalRule<R>(pub R);
+
+impl<T, R> Rule<Matrix<T>> for LocalRule<R>
+where
+    T: Default,
+    R: CellRule<T>,
+{
+    fn step(&self, current: &Matrix<T>, next: &mut Matrix<T>, _generation: usize) {
+        for (index, target) in next.data.iter_mut().enumerate() {
+            let (x, y) = (index % current.width, index / current.width);
+            let neighbours = current
+                .neighbours_iter(self.0.offsets(), x, y)
+                .map(|(value, _)| value)
+                .collect::<Vec<_>>();
+
+            *target = self
+                .0
+                .next(current.get(x, y), &neighbours)
+                .unwrap_or_default();
+        }
+    }
+}
+
+impl<T, R> Rule<SparseGrid<T>> for LocalRule<R>
+where
+    R: CellRule<T>,
+{
+    fn step(&self, current: &SparseGrid<T>, next: &mut SparseGrid<T>, _generation: usize) {
+        let candidates = current
+            .coords()
+            .flat_map(|coord| {
+                self.0
+                    .offsets()
+                    .iter()
+                    .filter_map(move |&offset| coord.checked_offset(offset))
+                    .chain([coord])
+            })
+            .collect::<HashSet<Coord>>();
+
+        for coord in candidates {
+            let neighbours = current
+                .neighbours_iter(self.0.offsets(), coord.x(), coord.y())
+                .map(|(value, _)| value)
+                .collect::<Vec<_>>();
+
+            if let Some(value) = self.0.next(current.get_at(coord), &neighbours) {
+                next.set_at(coord, value);
+            }
+        }
+    }
+}
+
+pub struct Automaton<B, R> {
+    current: B,
+    next: B,
+    rule: R,
+    generation: usize,
+}
+
+impl<B: Backend, R: Rule<B>> Automaton<B, R> {
+    pub fn new(state: B, rule: R) -> Self {
+        Self {
+            next: state.clone(),
+            current: state,
+            rule,
+            generation: 0,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn state(&self) -> &B {
+        &self.current
+    }
+
+    #[allow(dead_code)]
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Advances one generation, returning whether the state changed.
+    pub fn step(&mut self) -> bool {
+        self.next.reset_like(&self.current);
+        self.rule
+            .step(&self.current, &mut self.next, self.generation);
+
+        std::mem::swap(&mut self.current, &mut self.next);
+        self.generation += 1;
+
+        self.current != self.next
+    }
+
+    pub fn run(&mut self, generations: usize) -> &B {
+        for _ in 0..generations {
+            self.step();
+        }
+
+        &self.current
+    }
+
+    /// Steps until a generation leaves the state unchanged and returns that generation.
+    pub fn run_until_stable(&mut self) -> usize {
+        while self.step() {}
+
+        self.generation
+    }
+
+    /// Steps until a state repeats, giving up after `limit` generations.
+    #[allow(dead_code)]
+    pub fn find_cycle(&mut self, limit: usize) -> Option<Cycle> {
+        let offset = self.generation;
+        let mut detector = CycleDetector::new();
+        detector.observe(self.current.key());
+
+        for _ in 0..limit {
+            self.step();
+
+            if let Some(cycle) = detector.observe(self.current.key()) {
+                return Some(Cycle {
+                    start: cycle.start + offset,
+                    ..cycle
+                });
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::MATRIX_NEIGHBOURS_8;
+
+    struct Life;
+
+    impl CellRule<bool> for Life {
+        fn offsets(&self) -> &[(i32, i32)] {
+            &MATRIX_NEIGHBOURS_8
+        }
+
+        fn next(&self, cell: Option<&bool>, neighbours: &[&bool]) -> Option<bool> {
+            let alive = neighbours.iter().filter(|&&&alive| alive).count();
+            let is_alive = cell.copied().unwrap_or_default();
+
+            (alive == 3 || (is_alive && alive == 2)).then_some(true)
+        }
+    }
+
+    #[test]
+    fn test_dense_blinker() {
+        let blinker =
+            Matrix::parse_with(".....\n..#..\n..#..\n..#..\n.....", |ch, _| Ok(ch == '#')).unwrap();
+        let mut automaton = Automaton::new(blinker.clone(), LocalRule(Life));
+
+        assert!(automaton.step());
+        assert_eq!(
+            automaton.state().row(2).unwrap(),
+            [false, true, true, true, false]
+        );
+        assert_eq!(automaton.generation(), 1);
+
+        assert_eq!(
+            automaton.find_cycle(10),
+            Some(Cycle {
+                start: 1,
+                length: 2
+            })
+        );
+        assert_eq!(automaton.generation(), 3);
+
+        automaton.step();
+        assert!(automaton.state() == &blinker);
+    }
+
+    #[test]
+    fn test_sparse_block_is_stable() {
+        let block = SparseGrid::from_iter(
+            [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(x, y)| (Coord::xy(x, y), true)),
+        );
+        let mut automaton = Automaton::new(block.clone(), LocalRule(Life));
+
+        assert_eq!(automaton.run_until_stable(), 1);
+        assert!(automaton.state() == &block);
+
+        let glider = SparseGrid::from_iter(
+            [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].map(|(x, y)| (Coord::xy(x, y), true)),
+        );
+        let mut automaton = Automaton::new(glider, LocalRule(Life));
+        let moved = automaton.run(4);
+        assert_eq!(moved.len(), 5);
+        assert_eq!(moved.bounds(), Some((Coord::xy(1, 1), Coord::xy(3, 3))));
+
+        let reversed = SparseGrid::from_iter(
+            [(1, 1), (0, 1), (1, 0), (0, 0)].map(|(x, y)| (Coord::xy(x, y), true)),
+        );
+        assert_eq!(reversed.key(), block.key());
+        assert_ne!(moved.key(), block.key());
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use fxhash::FxHasher;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// Earliest step whose state equals the state after `step` steps.
+    #[allow(dead_code)]
+    pub fn reduce(&self, step: usize) -> usize {
+        if step < self.start {
+            step
+        } else {
+            self.start + (step - self.start) % self.length
+        }
+    }
+
+    /// Value of a counter after `step` steps, given its value after any step before
+    /// `start + length`. The counter must grow by the same amount every cycle.
+    #[allow(dead_code)]
+    pub fn extrapolate<F>(&self, step: usize, mut value_at: F) -> i64
+    where
+        F: FnMut(usize) -> i64,
+    {
+        if step < self.start + self.length {
+            return value_at(step);
+        }
+
+        let cycles = ((step - self.start) / self.length) as i64;
+        let per_cycle = value_at(self.start + self.length) - value_at(self.start);
+
+        value_at(self.reduce(step)) + cycles * per_cycle
+    }
+}
+
+#[allow(dead_code)]
+pub fn fingerprint<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = FxHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Floyd's tortoise and hare over `initial, step(initial), ...`, which must eventually repeat.
+#[allow(dead_code)]
+pub fn floyd<S, F>(initial: S, mut step: F) -> Cycle
+where
+    S: Clone + PartialEq,
+    F: FnMut(&S) -> S,
+{
+    let mut tortoise = step(&initial);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        let halfway = step(&hare);
+        hare = step(&halfway);
+    }
+
+    let mut start = 0;
+    tortoise = initial;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    let mut length = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        length += 1;
+    }
+
+    Cycle { start, length }
+}
+
+/// Brent's algorithm; same contract as `floyd` with fewer calls to `step`.
+#[allow(dead_code)]
+pub fn brent<S, F>(initial: S, mut step: F) -> Cycle
+where
+    S: Clone + PartialEq,
+    F: FnMut(&S) -> S,
+{
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = initial.clone();
+    let mut hare = step(&initial);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = step(&hare);
+        length += 1;
+    }
+
+    tortoise = initial.clone();
+    hare = initial;
+    for _ in 0..length {
+        hare = step(&hare);
+    }
+
+    let mut start = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    Cycle { start, length }
+}
+
+/// Finds a cycle by remembering a key for every state seen, for simulations that
+/// mutate their state in place.
+#[derive(Debug, Clone)]
+pub struct CycleDetector<K> {
+    seen: HashMap<K, usize>,
+    step: usize,
+}
+
+impl<K> Default for CycleDetector<K> {
+    fn default() -> Self {
+        Self {
+            seen: HashMap::new(),
+            step: 0,
+        }
+    }
+}
+
+impl<K: Hash + Eq> CycleDetector<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the key of the state after the next step, starting from step 0,
+    /// and returns the cycle once a key repeats.
+    pub fn observe(&mut self, key: K) -> Option<Cycle> {
+        let step = self.step;
+        self.step += 1;
+
+        self.seen.insert(key, step).map(|start| Cycle {
+            start,
+            length: step - start,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn naive(initial: u64, step: impl Fn(u64) -> u64) -> Cycle {
+        let mut seen = vec![initial];
+        loop {
+            let next = step(*seen.last().unwrap());
+            if let Some(start) = seen.iter().position(|&v| v == next) {
+                return Cycle {
+                    start,
+                    length: seen.len() - start,
+                };
+            }
+            seen.push(next);
+        }
+    }
+
+    #[test]
+    fn test_floyd_and_brent() {
+        for modulus in [7, 255, 1000, 4093] {
+            for initial in [0, 3, 42] {
+                let step = |&x: &u64| (x * x + 1) % modulus;
+                let expected = naive(initial, |x| step(&x));
+
+                assert_eq!(floyd(initial, step), expected);
+                assert_eq!(brent(initial, step), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cycle_detector_and_extrapolate() {
+        // 0, 1, 2, 3, 4, 2, 3, 4, ...
+        let state = |step: usize| if step < 2 { step } else { 2 + (step - 2) % 3 };
+
+        let mut detector = CycleDetector::new();
+        let cycle = (0..)
+            .find_map(|step| detector.observe(state(step)))
+            .unwrap();
+        assert_eq!(
+            cycle,
+            Cycle {
+                start: 2,
+                length: 3
+            }
+        );
+        assert_eq!(cycle.reduce(1), 1);
+        assert_eq!(cycle.reduce(1_000_000), state(1_000_000));
+
+        // A counter that adds the current state every step.
+        let totals = (0..10)
+            .scan(0, |total, step| {
+                *total += state(step) as i64;
+                Some(*total)
+            })
+            .collect::<Vec<_>>();
+        let total_at = |step: usize| (0..=step).map(|s| state(s) as i64).sum::<i64>();
+
+        for step in [0, 4, 9, 100, 12345] {
+            assert_eq!(cycle.extrapolate(step, |s| totals[s]), total_at(step));
+        }
+        assert_eq!(fingerprint(&[1, 2]), fingerprint(&vec![1, 2][..]));
+    }
+}
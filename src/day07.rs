@@ -10,6 +10,7 @@ pub struct DaySolution {
     pub filename: &'static str,
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 enum ShellLogEntry {
     ListCommand,
@@ -16,15 +16,12 @@ impl DaySolution {}
 
 impl Solution for DaySolution {
     fn part1(&self, input: &str) -> Result<Box<dyn Display>> {
-        let count = Matrix::<u8>::from(input)
-            .unwrap()
+        let count = Matrix::<u8>::from(input)?
             .iter_with_self()
             .filter(|&(v, (x, y), matrix)| {
-                (x == 0 || y == 0 || x == matrix.width - 1 || y == matrix.height - 1)
-                    || (0..x).all(|x| matrix.get(x, y).unwrap() < v)
-                    || (x + 1..matrix.width).all(|x| matrix.get(x, y).unwrap() < v)
-                    || (0..y).all(|y| matrix.get(x, y).unwrap() < v)
-                    || (y + 1..matrix.height).all(|y| matrix.get(x, y).unwrap() < v)
+                matrix
+                    .rays(&MATRIX_NEIGHBOURS_4, x, y)
+                    .any(|mut ray| ray.all(|(value, _)| value < v))
             })
             .count();
 
@@ -32,31 +29,16 @@ impl Solution for DaySolution {
     }
 
     fn part2(&self, input: &str) -> Result<Box<dyn Display>> {
-        let max_scenic = Matrix::<u8>::from(input).unwrap().iter_with_self().fold(
-            0,
-            |max_scenic, (v, (x, y), matrix)| {
-                MATRIX_NEIGHBOURS_4
-                    .iter()
-                    .fold(1, |result, (dx, dy)| {
-                        let mut nx = x as i32;
-                        let mut ny = y as i32;
-                        let mut count = 0;
-
-                        while let Some(value) = matrix.get(nx + dx, ny + dy) {
-                            count += 1;
-
-                            nx += dx;
-                            ny += dy;
-
-                            if value >= v {
-                                break;
-                            }
-                        }
-                        result * count
-                    })
-                    .max(max_scenic)
-            },
-        );
+        let max_scenic = Matrix::<u8>::from(input)?
+            .iter_with_self()
+            .map(|(v, (x, y), matrix)| {
+                matrix
+                    .rays(&MATRIX_NEIGHBOURS_4, x, y)
+                    .map(|ray| ray.take_until(|value| value >= v).count())
+                    .product::<usize>()
+            })
+            .max()
+            .unwrap_or_default();
 
         Ok(Box::new(max_scenic))
     }
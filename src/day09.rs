@@ -4,6 +4,8 @@ use anyhow::Result;
 use aoc::{Runnable, Solution};
 use aoc_derive::Runner;
 
+use crate::geometry::Point2;
+
 #[derive(Runner)]
 #[aoc(file = "inputs/day09.txt")]
 pub struct DaySolution {
@@ -24,57 +26,36 @@ struct Move {
     steps: i32,
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
-struct Position(i32, i32);
-
-struct Rope(Vec<Position>);
+struct Rope(Vec<Point2>);
 
 impl Rope {
     fn new(size: usize) -> Self {
-        Self(vec![Position(0, 0); size])
+        Self(vec![Point2::default(); size])
     }
 
     fn move_direction(&mut self, direction: Direction) {
-        let mut position = self.0[0];
-
-        match direction {
-            Direction::Right => position.0 += 1,
-            Direction::Left => position.0 -= 1,
-            Direction::Up => position.1 -= 1,
-            Direction::Down => position.1 += 1,
-        }
+        self.0[0] += match direction {
+            Direction::Right => Point2::xy(1, 0),
+            Direction::Left => Point2::xy(-1, 0),
+            Direction::Up => Point2::xy(0, -1),
+            Direction::Down => Point2::xy(0, 1),
+        };
 
-        self.0[0] = position;
         for i in 1..self.0.len() {
             let other = self.0[i - 1];
-            let mut current = self.0[i];
+            let current = self.0[i];
 
-            if !current.touching(&other) {
-                current.follow(&other);
-                self.0[i] = current;
+            if current.chebyshev(&other) > 1 {
+                self.0[i] += (other - current).signum();
             }
         }
     }
 
-    fn last(&self) -> Option<Position> {
+    fn last(&self) -> Option<Point2> {
         self.0.last().copied()
     }
 }
 
-impl Position {
-    fn touching(&self, other: &Self) -> bool {
-        let (dx, dy) = (self.0 - other.0, self.1 - other.1);
-        dx.abs() <= 1 && dy.abs() <= 1
-    }
-
-    fn follow(&mut self, other: &Self) {
-        let (dx, dy) = (self.0 - other.0, self.1 - other.1);
-
-        self.0 -= dx.signum();
-        self.1 -= dy.signum();
-    }
-}
-
 impl FromStr for Move {
     type Err = anyhow::Error;
 
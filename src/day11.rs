@@ -54,7 +54,7 @@ impl Monkey {
         while let Some(item) = self.items.pop_front() {
             let value = self.op.perform(item) / divider.unwrap_or(1);
 
-            if value % self.test == 0 {
+            if value.is_multiple_of(self.test) {
                 right.push(value % base);
             } else {
                 left.push(value % base);
@@ -4,7 +4,7 @@ use anyhow::Result;
 use aoc::{Runnable, Solution};
 use aoc_derive::Runner;
 
-use pathfinding::prelude::{bfs, Matrix};
+use crate::matrix::{Coord, Matrix, MATRIX_NEIGHBOURS_4};
 
 #[derive(Runner)]
 #[aoc(file = "inputs/day12.txt")]
@@ -12,51 +12,35 @@ pub struct DaySolution {
     pub filename: &'static str,
 }
 
-type Coord = (usize, usize);
-
 impl DaySolution {
     fn parse(&self, input: &str) -> Result<(Matrix<u8>, Coord, Coord)> {
-        let mut matrix = Matrix::from_rows(input.lines().map(|l| l.as_bytes().to_vec()))?;
-
-        let start = matrix
-            .indices()
-            .find(|&pos| matrix.get(pos).unwrap().eq(&b'S'))
-            .ok_or_else(|| anyhow::anyhow!("No start"))?;
-
-        let end = matrix
-            .indices()
-            .find(|&pos| matrix.get(pos).unwrap().eq(&b'E'))
-            .ok_or_else(|| anyhow::anyhow!("No end"))?;
-
-        *matrix.get_mut(start).unwrap() = b'a';
-        *matrix.get_mut(end).unwrap() = b'z';
-
-        Ok((matrix, start, end))
+        let (matrix, markers) = Matrix::parse_with_markers(input, &['S', 'E'], |ch, (x, y)| {
+            Ok(match ch {
+                'S' => b'a',
+                'E' => b'z',
+                'a'..='z' => ch as u8,
+                ch => anyhow::bail!("Invalid height {ch:?} at line {}, column {}", y + 1, x + 1),
+            })
+        })?;
+
+        Ok((matrix, markers.single('S')?, markers.single('E')?))
     }
 
-    fn path_len<T, U, V>(
+    fn path_len<U, V>(
         &self,
-        matrix: &Matrix<T>,
+        matrix: &Matrix<u8>,
         start: Coord,
-        mut can_move: U,
+        can_move: U,
         success: V,
     ) -> Result<usize>
     where
-        U: FnMut(&Coord, &Coord) -> bool,
-        V: Fn(&Coord) -> bool,
+        U: FnMut(&u8, &u8) -> bool,
+        V: FnMut(Coord, &u8) -> bool,
     {
-        bfs(
-            &start,
-            |&pos| {
-                matrix
-                    .neighbours(pos, false)
-                    .filter(|neighbour| can_move(&pos, neighbour))
-                    .collect::<Vec<_>>()
-            },
-            success,
-        )
-        .ok_or_else(|| anyhow::anyhow!("No path found"))
-        .map(|path| path.len() - 1)
+        matrix
+            .bfs(&MATRIX_NEIGHBOURS_4, start, can_move, success)
+            .ok_or_else(|| anyhow::anyhow!("No path found"))
+            .map(|path| path.len() - 1)
     }
 }
 
@@ -67,13 +51,8 @@ impl Solution for DaySolution {
         let result = self.path_len(
             &matrix,
             start,
-            |&current, &neighbour| {
-                let current = *matrix.get(current).unwrap() as i32;
-                let neighbour = *matrix.get(neighbour).unwrap() as i32;
-
-                neighbour - current <= 1
-            },
-            |&pos| pos == end,
+            |&current, &neighbour| neighbour as i32 - current as i32 <= 1,
+            |pos, _| pos == end,
         )?;
 
         Ok(Box::new(result))
@@ -84,13 +63,8 @@ impl Solution for DaySolution {
         let result = self.path_len(
             &matrix,
             end,
-            |&current, &neighbour| {
-                let current = *matrix.get(current).unwrap() as i32;
-                let neighbour = *matrix.get(neighbour).unwrap() as i32;
-
-                current - neighbour <= 1
-            },
-            |&pos| *matrix.get(pos).unwrap() == b'a',
+            |&current, &neighbour| current as i32 - neighbour as i32 <= 1,
+            |_, &height| height == b'a',
         )?;
 
         Ok(Box::new(result))
@@ -105,4 +79,16 @@ mod test {
 
     day_test!(day12, Part1, "inputs/day12_demo.txt", "31");
     day_test!(day12, Part2, "inputs/day12_demo.txt", "29");
+
+    #[test]
+    fn test_day12_invalid_height() {
+        let day = day12::DaySolution::default();
+
+        assert!(day.parse("Sab\nbcE").is_ok());
+        assert_eq!(
+            day.parse("Sab\nbéE").unwrap_err().to_string(),
+            "Invalid height 'é' at line 2, column 2"
+        );
+        assert!(day.parse("SaB\nbcE").is_err());
+    }
 }
@@ -1,78 +1,58 @@
-use std::{collections::HashMap, fmt::Display, str::FromStr};
+use std::fmt::Display;
 
 use anyhow::Result;
 use aoc::{Runnable, Solution};
 use aoc_derive::Runner;
 use itertools::Itertools;
 
+use crate::geometry::Point2;
+use crate::matrix::SparseGrid;
+
 #[derive(Runner)]
 #[aoc(file = "inputs/day14.txt")]
 pub struct DaySolution {
     pub filename: &'static str,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Point {
-    x: i32,
-    y: i32,
-}
+const SOURCE: Point2 = Point2::xy(500, 0);
 
-impl Point {
-    fn new((x, y): (i32, i32)) -> Self {
-        Self { x, y }
-    }
-
-    fn fall_in<T>(&self, space: &HashMap<Self, T>) -> Option<Self> {
-        [(0, 1), (-1, 1), (1, 1)]
-            .iter()
-            .map(|(dx, dy)| Point::new((self.x + dx, self.y + dy)))
-            .find(|point| !space.contains_key(point))
+impl DaySolution {
+    fn parse(&self, input: &str) -> SparseGrid<char> {
+        SparseGrid::from_iter(input.lines().flat_map(|line| {
+            line.split(" -> ")
+                .flat_map(|s| s.parse::<Point2>().ok())
+                .tuple_windows()
+                .flat_map(|(start, end)| Self::between(start, end).map(|p| (p, '#')))
+        }))
     }
 
-    fn between(start: &Point, end: &Point) -> impl Iterator<Item = Point> {
-        itertools::iproduct!(
-            start.x.min(end.x)..=start.x.max(end.x),
-            start.y.min(end.y)..=start.y.max(end.y)
-        )
-        .map(Point::new)
+    fn between(start: Point2, end: Point2) -> impl Iterator<Item = Point2> {
+        let (min, max) = (start.componentwise_min(&end), start.componentwise_max(&end));
+        itertools::iproduct!(min.x()..=max.x(), min.y()..=max.y()).map(|(x, y)| Point2::xy(x, y))
     }
-}
-
-impl FromStr for Point {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self> {
-        let (x, y) = s
-            .split_once(',')
-            .ok_or_else(|| anyhow::anyhow!("Invalid point!"))?;
 
-        Ok(Point {
-            x: x.parse()?,
-            y: y.parse()?,
-        })
+    fn fall_in<T>(point: Point2, space: &SparseGrid<T>) -> Option<Point2> {
+        [(0, 1), (-1, 1), (1, 1)]
+            .iter()
+            .filter_map(|&offset| point.checked_offset(offset))
+            .find(|&point| !space.contains(point))
     }
-}
 
-impl DaySolution {
-    fn parse(&self, input: &str) -> HashMap<Point, char> {
-        HashMap::from_iter(input.lines().flat_map(|line| {
-            line.split(" -> ")
-                .flat_map(|s| s.parse::<Point>().ok())
-                .tuple_windows()
-                .flat_map(|(start, end)| Point::between(&start, &end).map(|p| (p, '#')))
-        }))
+    fn sand_count(cave: &SparseGrid<char>) -> usize {
+        cave.iter().filter(|&(c, _)| *c == 'o').count()
     }
 }
 
 impl Solution for DaySolution {
     fn part1(&self, input: &str) -> Result<Box<dyn Display>> {
         let mut cave = self.parse(input);
-        let max_height = cave.keys().map(|p| p.y).max().unwrap();
-        let mut sand_point = Point::new((500, 0));
+        let (_, max) = cave.bounds().ok_or_else(|| anyhow::anyhow!("Empty cave"))?;
+        let max_height = max.y();
+        let mut sand_point = SOURCE;
 
         loop {
-            if let Some(new_sand_point) = sand_point.fall_in(&cave) {
-                if new_sand_point.y > max_height {
+            if let Some(new_sand_point) = Self::fall_in(sand_point, &cave) {
+                if new_sand_point.y() > max_height {
                     break;
                 }
 
@@ -80,39 +60,36 @@ impl Solution for DaySolution {
                 continue;
             }
 
-            cave.insert(sand_point, 'o');
-            sand_point = Point::new((500, 0));
+            cave.set_at(sand_point, 'o');
+            sand_point = SOURCE;
         }
 
-        Ok(Box::new(cave.values().filter(|&c| *c == 'o').count()))
+        Ok(Box::new(Self::sand_count(&cave)))
     }
 
     fn part2(&self, input: &str) -> Result<Box<dyn Display>> {
         let mut cave = self.parse(input);
-        let max_height = cave.keys().map(|p| p.y).max().unwrap();
-        let mut sand_point = Point::new((500, 0));
+        let (_, max) = cave.bounds().ok_or_else(|| anyhow::anyhow!("Empty cave"))?;
+        let max_height = max.y();
+        let mut sand_point = SOURCE;
 
-        cave.extend(
-            (0..1_000)
-                .map(|x| (Point::new((x, max_height + 2)), '#'))
-                .collect::<HashMap<_, _>>(),
-        );
+        cave.extend((0..1_000).map(|x| (Point2::xy(x, max_height + 2), '#')));
 
         loop {
-            if let Some(new_sand_point) = sand_point.fall_in(&cave) {
+            if let Some(new_sand_point) = Self::fall_in(sand_point, &cave) {
                 sand_point = new_sand_point;
                 continue;
             }
 
-            cave.insert(sand_point, 'o');
-            if sand_point.x == 500 && sand_point.y == 0 {
+            cave.set_at(sand_point, 'o');
+            if sand_point == SOURCE {
                 break;
             }
 
-            sand_point = Point::new((500, 0));
+            sand_point = SOURCE;
         }
 
-        Ok(Box::new(cave.values().filter(|&c| *c == 'o').count()))
+        Ok(Box::new(Self::sand_count(&cave)))
     }
 }
 
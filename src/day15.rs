@@ -5,6 +5,8 @@ use aoc::{Runnable, Solution};
 use aoc_derive::Runner;
 use itertools::Itertools;
 
+use crate::geometry::Point2;
+
 #[derive(Runner)]
 #[aoc(file = "inputs/day15.txt")]
 pub struct DaySolution {
@@ -15,47 +17,31 @@ trait HasLen {
     fn len(&self) -> usize;
 }
 
-impl HasLen for RangeInclusive<isize> {
+impl HasLen for RangeInclusive<i64> {
     fn len(&self) -> usize {
         (self.end() - self.start()) as usize
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Point {
-    x: isize,
-    y: isize,
-}
-
-impl Point {
-    fn new((x, y): (isize, isize)) -> Self {
-        Self { x, y }
-    }
-
-    fn manhattan(&self, other: &Self) -> usize {
-        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
-    }
-}
-
 struct System {
-    sensor: Point,
-    distance: usize,
+    sensor: Point2,
+    distance: u64,
 }
 
 impl System {
-    fn new(sensor: Point, beacon: Point) -> Self {
+    fn new(sensor: Point2, beacon: Point2) -> Self {
         let distance = sensor.manhattan(&beacon);
         Self { sensor, distance }
     }
 
-    fn range(&self, y: isize) -> Option<RangeInclusive<isize>> {
-        let offset = self.distance as isize - (self.sensor.y - y).abs();
-        (offset >= 0).then_some(self.sensor.x - offset..=self.sensor.x + offset)
+    fn range(&self, y: i64) -> Option<RangeInclusive<i64>> {
+        let offset = self.distance as i64 - (self.sensor.y() - y).abs();
+        (offset >= 0).then_some(self.sensor.x() - offset..=self.sensor.x() + offset)
     }
 }
 
 impl DaySolution {
-    fn parse(&self, input: &str) -> Vec<(Point, Point)> {
+    fn parse(&self, input: &str) -> Vec<(Point2, Point2)> {
         let pattern = crate::regex!(
             "^Sensor at x=(-?\\d+), y=(-?\\d+): closest beacon is at x=(-?\\d+), y=(-?\\d+)$"
         );
@@ -65,23 +51,23 @@ impl DaySolution {
             .map(|line| {
                 let captures = pattern.captures(line).unwrap();
 
-                let sensor = (
+                let sensor = [
                     captures.get(1).unwrap().as_str().parse().unwrap(),
                     captures.get(2).unwrap().as_str().parse().unwrap(),
-                );
-                let beacon = (
+                ];
+                let beacon = [
                     captures.get(3).unwrap().as_str().parse().unwrap(),
                     captures.get(4).unwrap().as_str().parse().unwrap(),
-                );
+                ];
 
-                (Point::new(sensor), Point::new(beacon))
+                (Point2::new(sensor), Point2::new(beacon))
             })
             .collect()
     }
 
-    fn combine_ranges(&self, ranges: Vec<RangeInclusive<isize>>) -> Vec<RangeInclusive<isize>> {
+    fn combine_ranges(&self, ranges: Vec<RangeInclusive<i64>>) -> Vec<RangeInclusive<i64>> {
         let mut ranges = ranges;
-        ranges.sort_by_key(|r| (*r.start()));
+        ranges.sort_by_key(|r| *r.start());
 
         let mut result = vec![];
         let mut current = ranges[0].clone();
@@ -175,8 +161,8 @@ mod test {
 
     #[test]
     fn test_day15_range() {
-        use super::{HasLen, Point, System};
-        let system = System::new(Point::new((0, 0)), Point::new((-3, -2)));
+        use super::{HasLen, Point2, System};
+        let system = System::new(Point2::xy(0, 0), Point2::xy(-3, -2));
         assert_eq!(system.distance, 5);
 
         let range = system.range(-4);
@@ -18,16 +18,56 @@ pub struct DaySolution {
     pub filename: &'static str,
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
-struct Valve {
-    id: String,
-    rate: i32,
-    connections: Vec<String>,
+const START: &str = "AA";
+const MINUTES: u32 = 30;
+/// Minutes spent teaching the elephant before both start working in part 2.
+const TRAINING: u32 = 4;
+
+type ValveId = u16;
+
+/// Tunnel network with valve names interned to compact indices.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct ValveGraph {
+    names: Vec<String>,
+    ids: HashMap<String, ValveId>,
+    rates: Vec<i32>,
+    tunnels: Vec<Vec<ValveId>>,
+    defined: Vec<bool>,
 }
 
-impl Valve {
-    fn parse(i: &str) -> IResult<&str, Valve> {
-        let (_, (_, id, _, rate, _, connections)) = tuple((
+impl ValveGraph {
+    fn parse(input: &str) -> Result<Self> {
+        let mut graph = Self::default();
+
+        for (number, line) in input.lines().enumerate() {
+            graph
+                .valve(line)
+                .map_err(|e| anyhow::anyhow!("Invalid valve on line {}: {e}", number + 1))?;
+        }
+
+        if let Some(id) = graph.defined.iter().position(|&defined| !defined) {
+            anyhow::bail!("Valve {} is referenced but never defined", graph.names[id]);
+        }
+
+        Ok(graph)
+    }
+
+    fn valve(&mut self, line: &str) -> Result<()> {
+        let (_, (name, rate, tunnels)) = Self::line(line).map_err(|e| anyhow::anyhow!("{e}"))?;
+
+        let id = self.intern(name)? as usize;
+        self.rates[id] = rate;
+        self.defined[id] = true;
+        self.tunnels[id] = tunnels
+            .into_iter()
+            .map(|name| self.intern(name))
+            .collect::<Result<_>>()?;
+
+        Ok(())
+    }
+
+    fn line(i: &str) -> IResult<&str, (&str, i32, Vec<&str>)> {
+        let (rest, (_, name, _, rate, _, tunnels)) = tuple((
             tag("Valve "),
             take(2usize),
             tag(" has flow rate="),
@@ -39,207 +79,432 @@ impl Valve {
             separated_list1(tag(", "), take(2usize)),
         ))(i)?;
 
-        Ok((
-            i,
-            Valve {
-                id: id.to_owned(),
-                rate,
-                connections: connections.into_iter().map(String::from).collect(),
-            },
-        ))
+        Ok((rest, (name, rate, tunnels)))
     }
-}
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct State {
-    opened: Vec<String>,
-    me: String,
-    total: i32,
-    elephant: Option<String>,
-}
+    fn intern(&mut self, name: &str) -> Result<ValveId> {
+        if let Some(&id) = self.ids.get(name) {
+            return Ok(id);
+        }
+
+        let id = ValveId::try_from(self.names.len())
+            .map_err(|_| anyhow::anyhow!("Too many valves to intern {name}"))?;
+        self.names.push(name.to_owned());
+        self.ids.insert(name.to_owned(), id);
+        self.rates.push(0);
+        self.tunnels.push(vec![]);
+        self.defined.push(false);
+
+        Ok(id)
+    }
 
-impl State {
-    fn new(current: impl ToString) -> Self {
-        State {
-            opened: vec![],
-            me: current.to_string(),
-            total: 0,
-            elephant: None,
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    fn id(&self, name: &str) -> Result<ValveId> {
+        self.ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Unknown valve {name}"))
+    }
+
+    /// Graphviz rendering of the tunnels; valves with flow are drawn as double circles.
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("graph valves {\n");
+
+        for (id, name) in self.names.iter().enumerate() {
+            let shape = if self.rates[id] > 0 {
+                "doublecircle"
+            } else {
+                "circle"
+            };
+            dot += &format!(
+                "  {name} [label=\"{name}\\n{}\", shape={shape}];\n",
+                self.rates[id]
+            );
         }
+
+        for (id, tunnels) in self.tunnels.iter().enumerate() {
+            for &other in tunnels {
+                let other = other as usize;
+                if id < other || !self.tunnels[other].contains(&(id as ValveId)) {
+                    dot += &format!("  {} -- {};\n", self.names[id], self.names[other]);
+                }
+            }
+        }
+
+        dot + "}\n"
     }
+}
 
-    fn release(&mut self, valves: &HashMap<String, Valve>) {
-        let mut released = 0;
-        for opened in self.opened.iter() {
-            let valve = valves.get(opened).unwrap();
-            released += valve.rate;
+/// One valve opened by an agent, with the minutes left when it starts flowing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Opening {
+    valve: String,
+    remaining: u32,
+}
+
+/// Certificate of a solution: the order in which each agent opens its valves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Plan {
+    minutes: u32,
+    delays: Vec<u32>,
+    pressure: i32,
+    orders: Vec<Vec<Opening>>,
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (agent, order) in self.orders.iter().enumerate() {
+            let order = order
+                .iter()
+                .map(|opening| format!("{}@{}", opening.valve, opening.remaining))
+                .join(" ");
+            writeln!(f, "agent {agent}: {order}")?;
         }
 
-        self.total += released;
+        write!(f, "pressure: {}", self.pressure)
     }
+}
 
-    fn my_possible_actions(
-        &self,
-        valves: &HashMap<String, Valve>,
-        possible_open: usize,
-    ) -> Vec<State> {
-        let mut actions = vec![];
-        let valve = valves.get(&self.me).unwrap();
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    Wait,
+    Move(String),
+    Open(String),
+}
 
-        // Open current
-        if !self.opened.contains(&self.me) && valve.rate > 0 {
-            let mut new_state = self.clone();
-            new_state.opened.push(self.me.clone());
-            actions.push(new_state);
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Wait => write!(f, "wait"),
+            Action::Move(valve) => write!(f, "move {valve}"),
+            Action::Open(valve) => write!(f, "open {valve}"),
         }
+    }
+}
 
-        // Only release if all opened
-        if possible_open == self.opened.len() {
-            actions.push(self.clone());
+/// Best pressure, and the opening order achieving it, keyed by the set of valves opened.
+type BestByMask = HashMap<u64, (i32, Vec<(usize, u32)>)>;
+
+/// Entries of a `BestByMask`, best first.
+type Ranked = Vec<(u64, i32, Vec<(usize, u32)>)>;
+
+/// Valves with a non-zero flow rate plus the start, and the shortest distances between them.
+#[derive(Debug)]
+struct Network {
+    rates: Vec<i32>,
+    distances: Vec<Vec<u32>>,
+    start: usize,
+    valves: Vec<String>,
+    kept: Vec<usize>,
+    next_hop: Vec<Vec<usize>>,
+}
+
+impl Network {
+    fn new(graph: &ValveGraph, start: &str) -> Result<Self> {
+        let n = graph.len();
+        let mut all = vec![vec![u32::MAX / 2; n]; n];
+        let mut next_hop = vec![(0..n).collect::<Vec<_>>(); n];
+        for (i, tunnels) in graph.tunnels.iter().enumerate() {
+            all[i][i] = 0;
+            for &j in tunnels {
+                all[i][j as usize] = 1;
+            }
         }
 
-        // Move to neighbour
-        for neighbour in valve.connections.iter() {
-            let mut neighbour_state = self.clone();
-            neighbour_state.me = neighbour.clone();
+        for k in 0..n {
+            for i in 0..n {
+                for j in 0..n {
+                    if all[i][k] + all[k][j] < all[i][j] {
+                        all[i][j] = all[i][k] + all[k][j];
+                        next_hop[i][j] = next_hop[i][k];
+                    }
+                }
+            }
+        }
 
-            actions.push(neighbour_state);
+        let start = graph.id(start)? as usize;
+        let kept = (0..n)
+            .filter(|&i| i == start || graph.rates[i] > 0)
+            .collect::<Vec<_>>();
+        if kept.len() > 64 {
+            anyhow::bail!("Too many valves with flow: {}", kept.len());
         }
 
-        actions
+        Ok(Self {
+            rates: kept.iter().map(|&i| graph.rates[i]).collect(),
+            distances: kept
+                .iter()
+                .map(|&i| kept.iter().map(|&j| all[i][j]).collect())
+                .collect(),
+            start: kept.iter().position(|&i| i == start).unwrap(),
+            valves: graph.names.clone(),
+            next_hop,
+            kept,
+        })
     }
 
-    fn is_opening(&self, other: &State) -> bool {
-        self.me == other.me
+    /// Every set of valves one agent can open in time.
+    fn best_by_mask(&self, minutes: u32) -> BestByMask {
+        let mut best = HashMap::new();
+        self.explore(self.start, minutes, 0, 0, &mut vec![], &mut best);
+        best
     }
 
-    fn my_possible_actions_with_elephant(
+    fn explore(
         &self,
-        valves: &HashMap<String, Valve>,
-        possible_open: usize,
-    ) -> Vec<State> {
-        if self.opened.len() == possible_open {
-            return vec![self.clone()];
-        }
-
-        let my_actions = self.my_possible_actions(valves, possible_open);
-        let mut actions = vec![];
-
-        for my_action in my_actions.iter() {
-            if self.elephant.is_none() {
-                // moving elephant
-                actions.extend(
-                    valves[&self.me]
-                        .connections
+        at: usize,
+        remaining: u32,
+        mask: u64,
+        pressure: i32,
+        order: &mut Vec<(usize, u32)>,
+        best: &mut BestByMask,
+    ) {
+        let entry = best.entry(mask).or_insert((-1, vec![]));
+        if pressure > entry.0 {
+            *entry = (pressure, order.clone());
+        }
+
+        for next in 0..self.kept.len() {
+            let cost = self.distances[at][next] + 1;
+            if self.rates[next] == 0 || mask & 1 << next != 0 || cost >= remaining {
+                continue;
+            }
+
+            let left = remaining - cost;
+            order.push((next, left));
+            self.explore(
+                next,
+                left,
+                mask | 1 << next,
+                pressure + self.rates[next] * left as i32,
+                order,
+                best,
+            );
+            order.pop();
+        }
+    }
+
+    /// Best plan for agents that start `delays[i]` minutes late and all stop after `minutes`.
+    fn solve(&self, minutes: u32, delays: &[u32]) -> Plan {
+        let mut tables = HashMap::new();
+        let tables = delays
+            .iter()
+            .map(|&delay| {
+                let budget = minutes.saturating_sub(delay);
+                tables
+                    .entry(budget)
+                    .or_insert_with(|| {
+                        self.best_by_mask(budget)
+                            .into_iter()
+                            .map(|(mask, (pressure, order))| (mask, pressure, order))
+                            .sorted_by_key(|&(_, pressure, _)| -pressure)
+                            .collect::<Ranked>()
+                    })
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+
+        let mut best = (-1, vec![]);
+        self.assign(&tables, delays, 0, 0, &mut vec![], &mut best);
+
+        let (pressure, chosen) = best;
+        Plan {
+            minutes,
+            delays: delays.to_vec(),
+            pressure,
+            orders: chosen
+                .iter()
+                .enumerate()
+                .map(|(agent, &index)| {
+                    tables[agent][index]
+                        .2
                         .iter()
-                        .cloned()
-                        .filter(|x| {
-                            x != if self.is_opening(my_action) {
-                                &self.me
-                            } else {
-                                &my_action.me
-                            }
+                        .map(|&(valve, remaining)| Opening {
+                            valve: self.name(valve).to_owned(),
+                            remaining,
                         })
-                        .map(|neighbour| {
-                            let mut neighbour_state = my_action.clone();
-                            neighbour_state.elephant = Some(neighbour);
-                            neighbour_state
-                        }),
-                );
-            } else {
-                let elephant = self.elephant.clone().unwrap();
-                let elephant_valve = valves.get(&elephant).unwrap();
-
-                // open
-                if elephant_valve.rate > 0
-                    && !self.opened.contains(&elephant)
-                    && (!self.is_opening(my_action) || self.me != elephant)
-                {
-                    let mut new_state = my_action.clone();
-                    new_state.opened.push(elephant.clone());
-
-                    actions.push(new_state);
-                }
+                        .collect()
+                })
+                .collect(),
+        }
+    }
 
-                // moving
-                actions.extend(
-                    valves[&elephant]
-                        .connections
-                        .iter()
-                        .cloned()
-                        .filter(|x| x != &self.me)
-                        .map(|neighbour| {
-                            let mut neighbour_state = my_action.clone();
-                            neighbour_state.elephant = Some(neighbour);
-                            neighbour_state
-                        }),
-                );
+    /// Branch and bound over disjoint valve sets, one per agent.
+    fn assign(
+        &self,
+        tables: &[Ranked],
+        delays: &[u32],
+        used: u64,
+        pressure: i32,
+        chosen: &mut Vec<usize>,
+        best: &mut (i32, Vec<usize>),
+    ) {
+        let agent = chosen.len();
+        if agent == tables.len() {
+            if pressure > best.0 {
+                *best = (pressure, chosen.clone());
             }
+            return;
         }
 
-        actions
+        let rest = tables[agent + 1..]
+            .iter()
+            .map(|table| table[0].1)
+            .sum::<i32>();
+        // Agents with the same delay are interchangeable, so only try one ordering of them.
+        let first = match agent {
+            0 => 0,
+            _ if delays[agent] == delays[agent - 1] => chosen[agent - 1],
+            _ => 0,
+        };
+
+        for (index, (mask, gain, _)) in tables[agent].iter().enumerate().skip(first) {
+            if pressure + gain + rest <= best.0 {
+                break;
+            }
+            if mask & used != 0 {
+                continue;
+            }
+
+            chosen.push(index);
+            self.assign(tables, delays, used | mask, pressure + gain, chosen, best);
+            chosen.pop();
+        }
     }
-}
 
-impl DaySolution {
-    fn parse(&self, input: &str) -> HashMap<String, Valve> {
-        HashMap::from_iter(input.lines().map(|line| {
-            let (_, valve) = Valve::parse(line).unwrap();
-            (valve.id.clone(), valve)
-        }))
+    /// Replays a plan from scratch and returns the pressure it releases.
+    #[allow(dead_code)]
+    fn verify(&self, plan: &Plan) -> Result<i32> {
+        let mut opened = HashSet::new();
+        let mut pressure = 0;
+
+        for (order, delay) in plan.orders.iter().zip(plan.delays.iter()) {
+            let (mut at, mut remaining) = (self.start, plan.minutes.saturating_sub(*delay));
+
+            for opening in order {
+                let valve = self.kept_index(&opening.valve)?;
+                if !opened.insert(valve) {
+                    anyhow::bail!("Valve {} opened twice", opening.valve);
+                }
+
+                remaining = remaining
+                    .checked_sub(self.distances[at][valve] + 1)
+                    .filter(|&left| left == opening.remaining)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Valve {} cannot open at {}",
+                            opening.valve,
+                            opening.remaining
+                        )
+                    })?;
+                pressure += self.rates[valve] * remaining as i32;
+                at = valve;
+            }
+        }
+
+        if pressure != plan.pressure {
+            anyhow::bail!("Plan releases {pressure}, not {}", plan.pressure);
+        }
+
+        Ok(pressure)
     }
 
-    fn solve<F>(&self, input: &str, minutes: usize, states_limit: usize, next_states: F) -> i32
-    where
-        F: Fn(&State, &HashMap<String, Valve>, usize) -> Vec<State>,
-    {
-        let valves = self.parse(input);
+    fn kept_index(&self, valve: &str) -> Result<usize> {
+        (0..self.kept.len())
+            .find(|&index| self.name(index) == valve)
+            .ok_or_else(|| anyhow::anyhow!("Valve {valve} has no flow"))
+    }
 
-        let openable = valves.iter().filter(|(_, valve)| valve.rate > 0).count();
+    /// Name of the valve at `index` among the kept ones.
+    fn name(&self, index: usize) -> &str {
+        &self.valves[self.kept[index]]
+    }
 
-        let mut states = HashSet::new();
-        let state = State::new("AA");
-        states.insert(state);
+    /// What every agent does in each minute of the plan.
+    fn schedules(&self, plan: &Plan) -> Result<Vec<Vec<Action>>> {
+        plan.orders
+            .iter()
+            .zip(plan.delays.iter())
+            .map(|(order, &delay)| {
+                let mut actions = vec![Action::Wait; delay.min(plan.minutes) as usize];
+                let mut at = self.kept[self.start];
+
+                for opening in order {
+                    let target = self.kept[self.kept_index(&opening.valve)?];
+                    while at != target {
+                        at = self.next_hop[at][target];
+                        actions.push(Action::Move(self.valves[at].clone()));
+                    }
+                    actions.push(Action::Open(opening.valve.clone()));
+                }
 
-        for _ in 0..minutes {
-            let mut new_states = HashSet::new();
+                if actions.len() > plan.minutes as usize {
+                    anyhow::bail!("Plan does not fit in {} minutes", plan.minutes);
+                }
+                actions.resize(plan.minutes as usize, Action::Wait);
 
-            for state in states.iter() {
-                let mut new_state = state.clone();
-                new_state.release(&valves);
-                new_states.extend(next_states(&new_state, &valves, openable));
-            }
+                Ok(actions)
+            })
+            .collect()
+    }
 
-            states = HashSet::from_iter(
-                new_states
-                    .into_iter()
-                    .sorted_by_key(|s: &State| s.total)
-                    .rev()
-                    .take(states_limit),
-            );
+    /// One row per minute with a column per agent.
+    fn timeline(&self, plan: &Plan) -> Result<String> {
+        let schedules = self.schedules(plan)?;
+        let header = (0..schedules.len())
+            .map(|agent| format!("{:<10}", format!("agent {agent}")))
+            .join("");
+        let mut timeline = format!("min {}\n", header.trim_end());
+
+        for minute in 0..plan.minutes as usize {
+            let row = schedules
+                .iter()
+                .map(|actions| format!("{:<10}", actions[minute].to_string()))
+                .join("");
+            timeline += &format!("{:>3} {}\n", minute + 1, row.trim_end());
         }
 
-        states.iter().map(|s| s.total).max().unwrap()
+        Ok(timeline)
+    }
+}
+
+impl DaySolution {
+    fn parse(&self, input: &str) -> Result<ValveGraph> {
+        ValveGraph::parse(input)
+    }
+
+    /// `dot` renders the tunnels for Graphviz, `timeline` the part 2 plan minute by minute.
+    pub fn view(&self, input: &str, name: &str) -> Result<String> {
+        let graph = self.parse(input)?;
+
+        match name {
+            "dot" => Ok(graph.to_dot()),
+            "timeline" => {
+                let network = Network::new(&graph, START)?;
+                let plan = network.solve(MINUTES, &[TRAINING, TRAINING]);
+                Ok(format!("{plan}\n{}", network.timeline(&plan)?))
+            }
+            _ => anyhow::bail!("Unknown view {name:?}, expected dot or timeline"),
+        }
     }
 }
 
 impl Solution for DaySolution {
     fn part1(&self, input: &str) -> Result<Box<dyn Display>> {
-        Ok(Box::new(self.solve(
-            input,
-            30,
-            1_000,
-            State::my_possible_actions,
-        )))
+        let network = Network::new(&self.parse(input)?, START)?;
+
+        Ok(Box::new(network.solve(MINUTES, &[0]).pressure))
     }
 
     fn part2(&self, input: &str) -> Result<Box<dyn Display>> {
-        Ok(Box::new(self.solve(
-            input,
-            26,
-            2_000,
-            State::my_possible_actions_with_elephant,
-        )))
+        let network = Network::new(&self.parse(input)?, START)?;
+
+        Ok(Box::new(
+            network.solve(MINUTES, &[TRAINING, TRAINING]).pressure,
+        ))
     }
 }
 
@@ -251,4 +516,107 @@ mod test {
 
     day_test!(day16, Part1, "inputs/day16_demo.txt", "1651");
     day_test!(day16, Part2, "inputs/day16_demo.txt", "1707");
+
+    #[test]
+    fn test_day16_certificate() {
+        use super::{Network, Opening};
+
+        let input = std::fs::read_to_string("inputs/day16_demo.txt").unwrap();
+        let graph = day16::DaySolution::default().parse(&input).unwrap();
+        let network = Network::new(&graph, "AA").unwrap();
+
+        let alone = network.solve(30, &[0]);
+        assert_eq!(
+            alone.orders[0]
+                .iter()
+                .map(|opening| opening.valve.as_str())
+                .collect::<Vec<_>>(),
+            ["DD", "BB", "JJ", "HH", "EE", "CC"]
+        );
+        assert_eq!(network.verify(&alone).unwrap(), 1651);
+
+        let pair = network.solve(26, &[0, 0]);
+        assert_eq!(network.verify(&pair).unwrap(), 1707);
+        assert!(network
+            .verify(&super::Plan {
+                minutes: 30,
+                ..pair.clone()
+            })
+            .is_err());
+
+        let mut forged = alone.clone();
+        forged.orders[0][0] = Opening {
+            valve: "DD".to_string(),
+            remaining: 29,
+        };
+        assert!(network.verify(&forged).is_err());
+        assert!(Network::new(&graph, "ZZ").is_err());
+    }
+
+    #[test]
+    fn test_day16_graph() {
+        use super::ValveGraph;
+
+        let input = std::fs::read_to_string("inputs/day16_demo.txt").unwrap();
+        let graph = ValveGraph::parse(&input).unwrap();
+        assert_eq!(graph.len(), 10);
+        assert_eq!(graph.id("AA").unwrap(), 0);
+
+        let jj = graph.id("JJ").unwrap() as usize;
+        assert_eq!(graph.rates[jj], 21);
+        assert_eq!(graph.tunnels[jj], [graph.id("II").unwrap()]);
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("graph valves {\n  AA [label=\"AA\\n0\", shape=circle];\n"));
+        assert!(dot.contains("  JJ [label=\"JJ\\n21\", shape=doublecircle];\n"));
+        assert_eq!(dot.matches(" -- ").count(), 10);
+
+        assert!(ValveGraph::parse("Valve AA has flow rate=0; tunnel leads to valve BB").is_err());
+        assert!(ValveGraph::parse("Valve AA has flow rate=x; tunnel leads to valve AA").is_err());
+
+        let mut graph = ValveGraph::default();
+        for i in 0..=u16::MAX {
+            graph.intern(&i.to_string()).unwrap();
+        }
+        assert!(graph.intern("AA").is_err());
+        assert_eq!(graph.intern("0").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_day16_agents() {
+        use super::{Action, Network, ValveGraph};
+
+        let input = std::fs::read_to_string("inputs/day16_demo.txt").unwrap();
+        let graph = ValveGraph::parse(&input).unwrap();
+        let network = Network::new(&graph, "AA").unwrap();
+
+        assert_eq!(network.solve(30, &[4, 4]).pressure, 1707);
+        assert_eq!(network.solve(30, &[]).pressure, 0);
+        assert_eq!(network.solve(30, &[0, 30]).pressure, 1651);
+
+        let trio = network.solve(30, &[0, 4, 8]);
+        assert_eq!(network.verify(&trio).unwrap(), trio.pressure);
+        assert!(trio.pressure >= network.solve(30, &[0, 4]).pressure);
+
+        let alone = network.solve(30, &[0]);
+        let schedule = &network.schedules(&alone).unwrap()[0];
+        // DD to BB has two shortest routes, so only pin down the valves and when they open.
+        assert_eq!(schedule[0], Action::Move("DD".to_string()));
+        assert_eq!(schedule[1], Action::Open("DD".to_string()));
+        assert!(matches!(schedule[2], Action::Move(_)));
+        assert_eq!(schedule[4], Action::Open("BB".to_string()));
+        assert_eq!(schedule[29], Action::Wait);
+
+        let timeline = network.timeline(&network.solve(26, &[0, 2])).unwrap();
+        let lines = timeline.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 27);
+        assert_eq!(lines[0], "min agent 0   agent 1");
+        assert!(lines[1].starts_with("  1 move ") && lines[1].ends_with("wait"));
+
+        let elsewhere = Network::new(&graph, "JJ").unwrap();
+        let plan = elsewhere.solve(10, &[0]);
+        assert_eq!(plan.orders[0][0].valve, "JJ");
+        assert_eq!(plan.orders[0][0].remaining, 9);
+        assert_eq!(elsewhere.verify(&plan).unwrap(), plan.pressure);
+    }
 }
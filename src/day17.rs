@@ -1,204 +1,283 @@
-use std::{collections::HashSet, fmt::Display};
+use std::fmt::{Display, Formatter};
 
 use anyhow::Result;
 use aoc::{Runnable, Solution};
 use aoc_derive::Runner;
 
+use crate::cycle::CycleDetector;
+
 #[derive(Runner)]
 #[aoc(file = "inputs/day17.txt")]
 pub struct DaySolution {
     pub filename: &'static str,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct Point {
-    x: usize,
-    y: usize,
-}
-
-#[derive(Debug, Clone)]
-struct Rock {
-    points: Vec<Point>,
+const ROCKS: [&str; 5] = [
+    "####",
+    ".#.\n###\n.#.",
+    "..#\n..#\n###",
+    "#\n#\n#\n#",
+    "##\n##",
+];
+
+const WIDTH: usize = 7;
+const SPAWN_LEFT: usize = 2;
+const SPAWN_GAP: usize = 3;
+/// Columns deeper than this below the top are treated alike when looking for a cycle.
+const SKYLINE_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Jet {
+    Left,
+    Right,
 }
 
-impl Rock {
-    fn new(shape: &str) -> Self {
-        let mut points = vec![];
-        for (y, line) in shape.lines().rev().enumerate() {
-            for (x, c) in line.chars().enumerate() {
-                if c == '#' {
-                    points.push(Point { x, y });
-                }
-            }
+impl Jet {
+    fn parse_all(input: &str) -> Result<Vec<Jet>> {
+        let jets = input
+            .trim()
+            .chars()
+            .enumerate()
+            .map(|(i, c)| match c {
+                '<' => Ok(Jet::Left),
+                '>' => Ok(Jet::Right),
+                c => Err(anyhow::anyhow!("Invalid jet {c:?} at position {}", i + 1)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if jets.is_empty() {
+            anyhow::bail!("Empty jet pattern");
         }
 
-        Rock { points }
+        Ok(jets)
     }
+}
 
-    fn all_available() -> Vec<Rock> {
-        vec![
-            Self::new("####"),
-            Self::new(".#.\n###\n.#."),
-            Self::new("..#\n..#\n###"),
-            Self::new("#\n#\n#\n#"),
-            Self::new("##\n##"),
-        ]
-    }
+/// Rock rows from the bottom up; bit `x` is set when column `x` is solid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Shape {
+    rows: Vec<u64>,
+    width: usize,
+}
 
-    fn move_left(&mut self, map: &HashSet<Point>) -> bool {
-        if self
-            .points
+impl Shape {
+    fn parse(shape: &str) -> Result<Self> {
+        let rows = shape
+            .lines()
+            .rev()
+            .map(|line| {
+                line.chars()
+                    .enumerate()
+                    .try_fold(0u64, |row, (x, c)| match c {
+                        '#' if x >= 64 => {
+                            Err(anyhow::anyhow!("Rock {shape:?} is wider than 64 columns"))
+                        }
+                        '#' => Ok(row | 1 << x),
+                        '.' => Ok(row),
+                        c => Err(anyhow::anyhow!("Invalid rock cell {c:?} in {shape:?}")),
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let width = rows
             .iter()
-            .any(|p| p.x == 0 || map.contains(&Point { x: p.x - 1, y: p.y }))
-        {
-            return false;
+            .map(|row| 64 - row.leading_zeros() as usize)
+            .max()
+            .unwrap_or_default();
+        if width == 0 {
+            anyhow::bail!("Empty rock {shape:?}");
         }
-        self.points.iter_mut().for_each(|p| p.x -= 1);
 
-        true
+        Ok(Self { rows, width })
     }
+}
 
-    fn move_right(&mut self, map: &HashSet<Point>) -> bool {
-        if self
-            .points
+#[derive(Debug, Clone)]
+struct Chamber {
+    width: usize,
+    shapes: Vec<Shape>,
+    jets: Vec<Jet>,
+    rows: Vec<u64>,
+    rocks: usize,
+    jet: usize,
+}
+
+impl Chamber {
+    fn new(width: usize, shapes: &[&str], jets: Vec<Jet>) -> Result<Self> {
+        let shapes = shapes
             .iter()
-            .any(|p| p.x == 6 || map.contains(&Point { x: p.x + 1, y: p.y }))
-        {
-            return false;
+            .map(|shape| Shape::parse(shape))
+            .collect::<Result<Vec<_>>>()?;
+
+        if shapes.is_empty() {
+            anyhow::bail!("No rock shapes");
+        }
+        if width > 64 {
+            anyhow::bail!("Chamber width {width} is wider than 64");
+        }
+        if let Some(shape) = shapes.iter().find(|shape| SPAWN_LEFT + shape.width > width) {
+            anyhow::bail!("Rock {:?} does not fit a chamber {width} wide", shape.rows);
         }
-        self.points.iter_mut().for_each(|p| p.x += 1);
 
-        true
+        Ok(Self {
+            width,
+            shapes,
+            jets,
+            rows: vec![],
+            rocks: 0,
+            jet: 0,
+        })
     }
 
-    fn top(&self) -> usize {
-        self.points.iter().map(|p| p.y).min().unwrap()
+    fn height(&self) -> usize {
+        self.rows.len()
     }
 
-    fn bottom(&self) -> usize {
-        self.points.iter().map(|p| p.y).max().unwrap()
+    fn collides(&self, shape: &Shape, x: usize, y: usize) -> bool {
+        x + shape.width > self.width
+            || shape.rows.iter().enumerate().any(|(dy, row)| {
+                self.rows
+                    .get(y + dy)
+                    .is_some_and(|settled| settled & row << x != 0)
+            })
     }
 
-    fn height(&self) -> usize {
-        self.bottom() - self.top() + 1
-    }
+    fn drop_rock(&mut self) {
+        let shape = &self.shapes[self.rocks % self.shapes.len()];
+        let (mut x, mut y) = (SPAWN_LEFT, self.height() + SPAWN_GAP);
 
-    fn move_by(&mut self, x: usize, y: usize) {
-        self.points.iter_mut().for_each(|p| {
-            p.x += x;
-            p.y += y;
-        });
-    }
+        loop {
+            let pushed = match self.jets[self.jet] {
+                Jet::Left => x.checked_sub(1),
+                Jet::Right => Some(x + 1),
+            };
+            self.jet = (self.jet + 1) % self.jets.len();
 
-    // fn moved_by(directions: &[char]) -> Self {
-    //     // let rock.move_by(2, 0);
-    //     // for c in directions.chars() {
-    //     //     match c {
-    //     //         'L' => self.move_left(&HashSet::new()),
-    //     //         'R' => self.move_right(&HashSet::new()),
-    //     //         'D' => self.down(&HashSet::new()),
-    //     //         _ => {}
-    //     //     }
-    //     // }
-    // }
-
-    fn down(&mut self, map: &HashSet<Point>) -> bool {
-        if self
-            .points
-            .iter()
-            .any(|p| p.y == 0 || map.contains(&Point { x: p.x, y: p.y - 1 }))
-        {
-            return false;
+            if let Some(pushed) = pushed.filter(|&pushed| !self.collides(shape, pushed, y)) {
+                x = pushed;
+            }
+
+            if y == 0 || self.collides(shape, x, y - 1) {
+                break;
+            }
+            y -= 1;
+        }
+
+        let top = y + shape.rows.len();
+        if self.rows.len() < top {
+            self.rows.resize(top, 0);
+        }
+        for (dy, row) in shape.rows.iter().enumerate() {
+            self.rows[y + dy] |= row << x;
         }
 
-        self.points.iter_mut().for_each(|p| p.y -= 1);
-        true
+        self.rocks += 1;
     }
-}
 
-impl DaySolution {
-    fn debug(&self, map: &HashSet<Point>, max_y: usize, current: Option<&Rock>) {
-        for y in (0..=max_y).rev() {
-            print!("|");
-            for x in 0..=6 {
-                if map.contains(&Point { x, y }) {
-                    print!("#");
-                } else {
-                    if let Some(rock) = current {
-                        if rock.points.contains(&Point { x, y }) {
-                            print!("@");
-                            continue;
-                        }
-                    }
-                    print!(".");
-                }
+    /// Depth of the highest solid cell in every column, measured from the top and capped
+    /// at `SKYLINE_DEPTH` so that columns which never fill up still repeat.
+    fn skyline(&self) -> Vec<usize> {
+        (0..self.width)
+            .map(|x| {
+                self.rows
+                    .iter()
+                    .rev()
+                    .position(|row| row & 1 << x != 0)
+                    .unwrap_or(self.height())
+                    .min(SKYLINE_DEPTH)
+            })
+            .collect()
+    }
+
+    /// Tower height after `count` rocks, skipping ahead once the chamber starts repeating.
+    fn height_after(&mut self, count: usize) -> usize {
+        let mut detector = CycleDetector::new();
+        let mut heights = vec![];
+
+        loop {
+            heights.push(self.height());
+            if self.rocks == count {
+                return self.height();
             }
-            print!("|");
-            println!();
-        }
 
-        println!("+-------+");
+            let key = (self.rocks % self.shapes.len(), self.jet, self.skyline());
+            if let Some(cycle) = detector.observe(key) {
+                return cycle.extrapolate(count, |step| heights[step] as i64) as usize;
+            }
+
+            self.drop_rock();
+        }
     }
 }
 
-impl Solution for DaySolution {
-    fn part1(&self, input: &str) -> Result<Box<dyn Display>> {
-        let rocks = Rock::all_available();
-        let mut map = HashSet::<Point>::new();
-        let mut y = 3;
-        let gas_diretions = input.chars().collect::<Vec<_>>();
-        let mut direction_index = 0;
-
-        // let now = std::time::Instant::now();
-
-        for rock_index in 0..5000 {
-            let mut rock = rocks[rock_index % rocks.len()].clone();
-            rock.move_by(2, y);
-            // println!("Rock: #{i}");
-            // self.debug(&map, y + rock.height() - 1, Some(&rock));
-
-            // if i % 1_000_000i64 == 0 {
-            //     println!("i: {}", i);
-
-            //     let elapsed = now.elapsed();
-            //     println!("Elapsed: {:?}", elapsed);
-            // }
-
-            loop {
-                let direction = gas_diretions[direction_index % gas_diretions.len()];
-                if direction == '>' {
-                    _ = !rock.move_right(&map);
-                } else {
-                    _ = !rock.move_left(&map);
-                }
-
-                // self.debug(&map, y + rock.height() - 1, Some(&rock));
-
-                direction_index += 1;
-                if !rock.down(&map) {
-                    break;
-                }
+impl Display for Chamber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in self.rows.iter().rev() {
+            write!(f, "|")?;
+            for x in 0..self.width {
+                write!(f, "{}", if row & 1 << x != 0 { '#' } else { '.' })?;
             }
+            writeln!(f, "|")?;
+        }
 
-            map.extend(rock.points.iter().cloned());
+        writeln!(f, "+{}+", "-".repeat(self.width))
+    }
+}
 
-            // println!();
-            // println!();
+impl DaySolution {
+    fn chamber(&self, input: &str, width: usize, shapes: &[&str]) -> Result<Chamber> {
+        Chamber::new(width, shapes, Jet::parse_all(input)?)
+    }
 
-            y = y.max(rock.top() + rock.height() + 3);
+    /// `chamber [rocks] [width] [shapes]` draws the chamber after `rocks` rocks (2022 by
+    /// default). Shapes are given as `####,.#./###/.#.`, rows split by `/`, rocks by `,`.
+    pub fn view(&self, input: &str, name: &str, options: &[String]) -> Result<String> {
+        if name != "chamber" {
+            anyhow::bail!("Unknown view {name:?}, expected chamber");
         }
 
-        // dbg!()
-        self.debug(&map, y, None);
-        let max_y = map.iter().map(|p| p.y).max().unwrap() + 1;
+        let rocks = match options.first() {
+            Some(rocks) => rocks
+                .parse::<usize>()
+                .map_err(|_| anyhow::anyhow!("Invalid rock count {rocks:?}"))?,
+            None => 2022,
+        };
+        let width = match options.get(1) {
+            Some(width) => width
+                .parse::<usize>()
+                .map_err(|_| anyhow::anyhow!("Invalid chamber width {width:?}"))?,
+            None => WIDTH,
+        };
+        let shapes = match options.get(2) {
+            Some(shapes) => shapes
+                .split(',')
+                .map(|shape| shape.replace('/', "\n"))
+                .collect::<Vec<_>>(),
+            None => ROCKS.map(str::to_owned).to_vec(),
+        };
+
+        let shapes = shapes.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut chamber = self.chamber(input, width, &shapes)?;
+        for _ in 0..rocks {
+            chamber.drop_rock();
+        }
 
-        dbg!(max_y);
+        Ok(chamber.to_string())
+    }
+}
 
-        Ok(Box::new(max_y))
+impl Solution for DaySolution {
+    fn part1(&self, input: &str) -> Result<Box<dyn Display>> {
+        Ok(Box::new(
+            self.chamber(input, WIDTH, &ROCKS)?.height_after(2022),
+        ))
     }
 
-    fn part2(&self, _input: &str) -> Result<Box<dyn Display>> {
-        Ok(Box::new(0))
+    fn part2(&self, input: &str) -> Result<Box<dyn Display>> {
+        Ok(Box::new(
+            self.chamber(input, WIDTH, &ROCKS)?
+                .height_after(1_000_000_000_000),
+        ))
     }
 }
 
@@ -209,5 +288,70 @@ mod test {
     use paste::paste;
 
     day_test!(day17, Part1, "inputs/day17_demo.txt", "3068");
-    day_test!(day17, Part2, "inputs/day17_demo.txt", "unknown");
+    day_test!(day17, Part2, "inputs/day17_demo.txt", "1514285714288");
+
+    #[test]
+    fn test_day17_chamber() {
+        use super::{Chamber, Jet, ROCKS};
+
+        let jets = Jet::parse_all(">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>\n").unwrap();
+        assert!(Jet::parse_all("<>x").is_err());
+
+        let mut chamber = Chamber::new(7, &ROCKS, jets.clone()).unwrap();
+        for _ in 0..3 {
+            chamber.drop_rock();
+        }
+        assert_eq!(
+            chamber.to_string(),
+            "|..#....|\n|..#....|\n|####...|\n|..###..|\n|...#...|\n|..####.|\n+-------+\n"
+        );
+
+        let simulated = (0..200).fold(
+            Chamber::new(9, &["##", "#\n#"], jets.clone()).unwrap(),
+            |mut chamber, _| {
+                chamber.drop_rock();
+                chamber
+            },
+        );
+        let mut skipped = Chamber::new(9, &["##", "#\n#"], jets.clone()).unwrap();
+        assert_eq!(skipped.height_after(200), simulated.height());
+
+        assert!(Chamber::new(3, &ROCKS, jets).is_err());
+
+        // Always blown left, the rightmost columns never fill and their depth keeps growing.
+        let left = Jet::parse_all("<").unwrap();
+        let simulated = (0..2000).fold(
+            Chamber::new(7, &ROCKS, left.clone()).unwrap(),
+            |mut chamber, _| {
+                chamber.drop_rock();
+                chamber
+            },
+        );
+        assert_eq!(simulated.skyline()[6], 64);
+        let mut skipped = Chamber::new(7, &ROCKS, left.clone()).unwrap();
+        assert_eq!(skipped.height_after(2000), simulated.height());
+        let mut skipped = Chamber::new(7, &ROCKS, left).unwrap();
+        assert!(skipped.height_after(1_000_000_000_000) > 0);
+
+        let day = day17::DaySolution::default();
+        let view = |options: &[&str]| {
+            let options = options.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+            day.view(">", "chamber", &options)
+        };
+        assert_eq!(view(&["3"]).unwrap(), "|......#|\n|......#|\n|....###|\n|.....#.|\n|....###|\n|.....#.|\n|...####|\n+-------+\n");
+        assert_eq!(
+            view(&["2", "4", "##,#/#"]).unwrap(),
+            "|...#|\n|...#|\n|..##|\n+----+\n"
+        );
+        assert!(view(&["x"]).is_err());
+        assert!(day.view(">", "tower", &[]).is_err());
+
+        let wide = format!("{}#", ".".repeat(64));
+        assert_eq!(
+            Chamber::new(7, &[wide.as_str()], vec![Jet::Left])
+                .unwrap_err()
+                .to_string(),
+            format!("Rock {wide:?} is wider than 64 columns")
+        );
+    }
 }
@@ -1,10 +1,12 @@
-use std::{collections::HashSet, fmt::Display, str::FromStr};
+use std::{collections::HashSet, fmt::Display};
 
 use anyhow::Result;
 use aoc::{Runnable, Solution};
 use aoc_derive::Runner;
 use itertools::{process_results, Itertools};
-use pathfinding::prelude::bfs;
+use pathfinding::prelude::bfs_reach;
+
+use crate::geometry::{bounds, orthogonal_offsets, Grid, Point3};
 
 #[derive(Runner)]
 #[aoc(file = "inputs/day18.txt")]
@@ -13,118 +15,49 @@ pub struct DaySolution {
 }
 
 impl DaySolution {
-    fn parse(&self, input: &str) -> Result<Vec<Cube1>> {
+    fn parse(&self, input: &str) -> Result<Vec<Point3>> {
         process_results(input.lines().map(str::parse), |it| it.collect_vec())
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-struct Cube1 {
-    x: i32,
-    y: i32,
-    z: i32,
-}
-
-impl Cube1 {
-    fn new(x: i32, y: i32, z: i32) -> Self {
-        Cube1 { x, y, z }
-    }
-
-    fn connected(&self, other: &Self) -> bool {
-        if self.x == other.x && self.y == other.y && self.z == other.z + 1 {
-            return true;
-        }
-
-        if self.x == other.x && self.y == other.y && self.z == other.z - 1 {
-            return true;
-        }
-
-        if self.x == other.x && self.z == other.z && self.y == other.y + 1 {
-            return true;
-        }
-
-        if self.x == other.x && self.z == other.z && self.y == other.y - 1 {
-            return true;
-        }
-
-        if self.z == other.z && self.y == other.y && self.x == other.x + 1 {
-            return true;
-        }
-
-        if self.z == other.z && self.y == other.y && self.x == other.x - 1 {
-            return true;
-        }
-
-        false
-    }
-}
-
-impl FromStr for Cube1 {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut iter = s.split(',');
-        let x = iter.next().unwrap().parse::<i32>()?;
-        let y = iter.next().unwrap().parse::<i32>()?;
-        let z = iter.next().unwrap().parse::<i32>()?;
-
-        Ok(Cube1::new(x, y, z))
-    }
-}
-
 impl Solution for DaySolution {
     fn part1(&self, input: &str) -> Result<Box<dyn Display>> {
-        let cubes = self.parse(input)?;
-        let result = itertools::iproduct!(cubes.iter(), cubes.iter()).fold(
-            cubes.len() * 6,
-            |acc, (c1, c2)| {
-                if c1 != c2 && c1.connected(c2) {
-                    acc - 1
-                } else {
-                    acc
-                }
-            },
-        );
+        let cubes: HashSet<Point3> = HashSet::from_iter(self.parse(input)?);
+        let offsets = orthogonal_offsets();
+
+        let result = cubes
+            .iter()
+            .flat_map(|cube| cube.neighbours(&offsets))
+            .filter(|neighbour| !cubes.contains(neighbour))
+            .count();
 
         Ok(Box::new(result))
     }
 
     fn part2(&self, input: &str) -> Result<Box<dyn Display>> {
-        let cubes: HashSet<Cube1> = HashSet::from_iter(self.parse(input)?.into_iter());
-
-        let (min_x, max_x) = cubes.iter().map(|c| c.x).minmax().into_option().unwrap();
-        let (min_y, max_y) = cubes.iter().map(|c| c.y).minmax().into_option().unwrap();
-        let (min_z, max_z) = cubes.iter().map(|c| c.z).minmax().into_option().unwrap();
-
-        let mut result = 0;
-        _ = bfs(
-            &Cube1::new(min_x - 1, min_y - 1, min_z - 1),
-            |&cube| {
-                result += cubes.iter().filter(|c| c.connected(&cube)).count();
-
-                let mut neighbours = vec![];
-                for offset in [-1, 1] {
-                    let x_cube = Cube1::new(cube.x + offset, cube.y, cube.z);
-                    let y_cube = Cube1::new(cube.x, cube.y + offset, cube.z);
-                    let z_cube = Cube1::new(cube.x, cube.y, cube.z + offset);
-
-                    if !cubes.contains(&x_cube) && x_cube.x >= min_x - 1 && x_cube.x <= max_x + 1 {
-                        neighbours.push(x_cube);
-                    }
-
-                    if !cubes.contains(&y_cube) && y_cube.y >= min_y - 1 && y_cube.y <= max_y + 1 {
-                        neighbours.push(y_cube);
-                    }
+        let cubes = self.parse(input)?;
+        let (min, max) =
+            bounds(cubes.iter().copied()).ok_or_else(|| anyhow::anyhow!("No cubes"))?;
+        let one = Point3::xyz(1, 1, 1);
 
-                    if !cubes.contains(&z_cube) && z_cube.z >= min_z - 1 && z_cube.z <= max_z + 1 {
-                        neighbours.push(z_cube);
-                    }
-                }
+        let mut lava = Grid::<bool, 3>::from_bounds(min - one, max + one);
+        for cube in cubes.iter() {
+            lava.set(cube, true);
+        }
 
-                neighbours
-            },
-            |&cube| cube == Cube1::new(max_x + 1, max_y + 1, max_z + 1),
-        );
+        let offsets = orthogonal_offsets();
+        let result: usize = bfs_reach(min - one, |air| {
+            lava.neighbours(air, &offsets)
+                .filter(|(&is_lava, _)| !is_lava)
+                .map(|(_, neighbour)| neighbour)
+                .collect::<Vec<_>>()
+        })
+        .map(|air| {
+            lava.neighbours(&air, &offsets)
+                .filter(|(&is_lava, _)| is_lava)
+                .count()
+        })
+        .sum();
 
         Ok(Box::new(result))
     }
@@ -1,9 +1,20 @@
-use std::{fmt::Display, str::FromStr};
+use std::{
+    fmt::{Display, Formatter},
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 use aoc::{Runnable, Solution};
 use aoc_derive::Runner;
-use pathfinding::prelude::bfs_reach;
+use nom::{
+    bytes::complete::tag,
+    character::complete::{alpha1, multispace0, multispace1, u32 as number},
+    combinator::all_consuming,
+    multi::{many1, separated_list1},
+    sequence::{delimited, preceded, separated_pair, terminated, tuple},
+    IResult,
+};
+use rayon::prelude::*;
 
 #[derive(Runner)]
 #[aoc(file = "inputs/day19.txt")]
@@ -11,161 +22,377 @@ pub struct DaySolution {
     pub filename: &'static str,
 }
 
-#[derive(Debug)]
+const GOAL: &str = "geode";
+
+/// Robot costs by resource. Robot `i` collects resource `i`; resources that only appear
+/// in costs come after the ones robots collect.
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Blueprint {
-    ore_robot_cost: usize,
-    clay_robot_cost: usize,
-    obsidian_robot_cost: (usize, usize),
-    geode_robot_cost: (usize, usize),
+    id: usize,
+    resources: Vec<String>,
+    costs: Vec<Vec<usize>>,
 }
 
-impl FromStr for Blueprint {
-    type Err = anyhow::Error;
+/// A parsed "Each <robot> robot costs <amount> <resource> and ..." sentence.
+type RobotCosts<'a> = (&'a str, Vec<(u32, &'a str)>);
 
-    fn from_str(s: &str) -> Result<Self> {
-        let items = s
-            .split_ascii_whitespace()
-            .flat_map(|a| a.parse::<usize>().ok())
-            .collect::<Vec<_>>();
+impl Blueprint {
+    fn parse_all(input: &str) -> Result<Vec<Self>> {
+        let (_, blueprints) = all_consuming(terminated(
+            many1(preceded(multispace0, Self::blueprint)),
+            multispace0,
+        ))(input)
+        .map_err(|e| anyhow::anyhow!("Invalid blueprint: {e}"))?;
+
+        blueprints
+            .into_iter()
+            .map(|(id, robots)| Self::new(id as usize, robots))
+            .collect()
+    }
+
+    fn blueprint(i: &str) -> IResult<&str, (u32, Vec<RobotCosts<'_>>)> {
+        tuple((
+            delimited(tag("Blueprint "), number, tag(":")),
+            many1(preceded(multispace1, Self::robot)),
+        ))(i)
+    }
+
+    fn robot(i: &str) -> IResult<&str, RobotCosts<'_>> {
+        let amount = separated_pair(number, multispace1, alpha1);
+
+        tuple((
+            delimited(tag("Each "), alpha1, tag(" robot costs ")),
+            terminated(separated_list1(tag(" and "), amount), tag(".")),
+        ))(i)
+    }
+
+    fn new(id: usize, robots: Vec<RobotCosts>) -> Result<Self> {
+        let mut resources = Vec::<String>::new();
+        for (robot, _) in &robots {
+            if resources.iter().any(|known| known == robot) {
+                anyhow::bail!("Blueprint {id} describes {robot} robots twice");
+            }
+            resources.push(robot.to_string());
+        }
+        for (_, name) in robots.iter().flat_map(|(_, costs)| costs) {
+            if !resources.iter().any(|known| known == name) {
+                resources.push(name.to_string());
+            }
+        }
+
+        let costs = robots
+            .iter()
+            .map(|(_, costs)| {
+                let mut row = vec![0; resources.len()];
+                for &(amount, name) in costs {
+                    row[resources.iter().position(|known| known == name).unwrap()] +=
+                        amount as usize;
+                }
+                row
+            })
+            .collect();
 
         Ok(Self {
-            ore_robot_cost: items[0],
-            clay_robot_cost: items[1],
-            obsidian_robot_cost: (items[2], items[3]),
-            geode_robot_cost: (items[4], items[5]),
+            id,
+            resources,
+            costs,
         })
     }
-}
 
-#[derive(Debug, Default, Eq, PartialEq, Hash, Clone, Copy)]
-struct State {
-    ore_robots: usize,
-    clay_robots: usize,
-    obsidian_robots: usize,
-    geode_robots: usize,
-    geocodes: usize,
-    ore: usize,
-    clay: usize,
-    obsidian: usize,
-    time: usize,
-}
+    fn resource(&self, name: &str) -> Result<usize> {
+        self.resources
+            .iter()
+            .position(|known| known == name)
+            .filter(|&resource| resource < self.costs.len())
+            .ok_or_else(|| anyhow::anyhow!("Blueprint {} has no {name} robot", self.id))
+    }
+
+    /// The single robot we start with collects the first resource listed.
+    fn start(&self, minutes: usize) -> State {
+        let mut robots = vec![0; self.resources.len()];
+        robots[0] = 1;
 
-impl State {
-    fn new() -> Self {
-        Self {
-            ore_robots: 1,
-            ..Default::default()
+        State {
+            robots,
+            stock: vec![0; self.resources.len()],
+            time: minutes,
         }
     }
 
-    fn increment(&mut self) {
-        self.ore += self.ore_robots;
-        self.clay += self.clay_robots;
-        self.obsidian += self.obsidian_robots;
-        self.geocodes += self.geode_robots;
-        self.time -= 1;
+    /// Most of `goal` that can be collected in `minutes`, and a build order achieving it.
+    fn optimize(&self, minutes: usize, goal: usize) -> Outcome {
+        // Only one robot can be built per minute, so never collect more than can be spent.
+        let caps = (0..self.resources.len())
+            .map(|resource| {
+                self.costs
+                    .iter()
+                    .map(|cost| cost[resource])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut search = Search {
+            blueprint: self,
+            caps,
+            goal,
+            minutes,
+            best: Outcome::default(),
+            builds: vec![],
+        };
+        search.run(self.start(minutes));
+
+        search.best
     }
-}
 
-impl DaySolution {
-    fn solve(&self, blueprint: &Blueprint, time: usize) -> usize {
-        let mut state = State::new();
-        state.time = time;
-
-        let max_ore_cost = blueprint
-            .ore_robot_cost
-            .max(blueprint.clay_robot_cost)
-            .max(blueprint.obsidian_robot_cost.0)
-            .max(blueprint.geode_robot_cost.0);
-
-        let mut result = 0;
-
-        _ = bfs_reach(state, |state| {
-            if state.time == 0 {
-                result = result.max(state.geocodes);
-                return vec![];
+    /// Plays `builds` minute by minute and returns how much of `goal` was collected.
+    #[allow(dead_code)]
+    fn replay(&self, minutes: usize, goal: usize, builds: &[Build]) -> Result<usize> {
+        let mut state = self.start(minutes);
+        let mut builds = builds.iter().peekable();
+        let nothing = vec![0; self.resources.len()];
+
+        for minute in 1..=minutes {
+            let build = builds.next_if(|build| build.minute == minute);
+            let cost = build.map_or(&nothing, |build| &self.costs[build.robot]);
+            if state
+                .stock
+                .iter()
+                .zip(cost)
+                .any(|(stock, cost)| stock < cost)
+            {
+                anyhow::bail!(
+                    "Cannot afford {} robot in minute {minute}",
+                    self.resources[build.unwrap().robot]
+                );
             }
 
-            let mut new_states = vec![];
+            for ((stock, robots), cost) in state.stock.iter_mut().zip(&state.robots).zip(cost) {
+                *stock = *stock - cost + robots;
+            }
+            if let Some(build) = build {
+                state.robots[build.robot] += 1;
+            }
+        }
 
-            if state.ore >= blueprint.geode_robot_cost.0
-                && state.obsidian >= blueprint.geode_robot_cost.1
-            {
-                let mut new_state = *state;
-                new_state.increment();
-                new_state.geode_robots += 1;
+        if let Some(build) = builds.next() {
+            anyhow::bail!(
+                "Build in minute {} is out of order or too late",
+                build.minute
+            );
+        }
 
-                new_state.ore -= blueprint.geode_robot_cost.0;
-                new_state.obsidian -= blueprint.geode_robot_cost.1;
-                new_states.push(new_state);
+        Ok(state.stock[goal])
+    }
+}
 
-                return new_states;
-            }
+/// A robot that starts collecting after `minute`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Build {
+    robot: usize,
+    minute: usize,
+}
 
-            if state.ore >= blueprint.obsidian_robot_cost.0
-                && state.clay >= blueprint.obsidian_robot_cost.1
-            // && state.obsidian_robots < blueprint.geode_robot_cost.1
-            {
-                let mut new_state = *state;
-                new_state.increment();
-                new_state.obsidian_robots += 1;
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Outcome {
+    collected: usize,
+    builds: Vec<Build>,
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    robots: Vec<usize>,
+    stock: Vec<usize>,
+    time: usize,
+}
 
-                new_state.ore -= blueprint.obsidian_robot_cost.0;
-                new_state.clay -= blueprint.obsidian_robot_cost.1;
-                new_states.push(new_state);
+struct Search<'a> {
+    blueprint: &'a Blueprint,
+    caps: Vec<usize>,
+    goal: usize,
+    minutes: usize,
+    best: Outcome,
+    builds: Vec<Build>,
+}
+
+impl Search<'_> {
+    fn run(&mut self, state: State) {
+        let collected = state.stock[self.goal] + state.robots[self.goal] * state.time;
+        if collected > self.best.collected {
+            self.best = Outcome {
+                collected,
+                builds: self.builds.clone(),
+            };
+        }
 
-                // return new_states;
+        // Even a new goal robot every remaining minute cannot beat the best.
+        let bound = collected + state.time * state.time.saturating_sub(1) / 2;
+        if bound <= self.best.collected {
+            return;
+        }
+
+        for robot in (0..self.blueprint.costs.len()).rev() {
+            if robot != self.goal
+                && state.robots[robot] * state.time + state.stock[robot]
+                    >= self.caps[robot] * state.time
+            {
+                continue;
             }
 
-            if state.ore >= blueprint.ore_robot_cost && state.ore_robots < max_ore_cost {
-                let mut new_state = *state;
-                new_state.increment();
-                new_state.ore_robots += 1;
-                new_state.ore -= blueprint.ore_robot_cost;
-                new_states.push(new_state);
+            let Some(wait) = self.wait_for(&state, robot) else {
+                continue;
+            };
+            if wait + 1 >= state.time {
+                continue;
             }
 
-            if state.ore >= blueprint.clay_robot_cost {
-                let mut new_state = *state;
-                new_state.increment();
-                new_state.clay_robots += 1;
-                new_state.ore -= blueprint.clay_robot_cost;
-                new_states.push(new_state);
+            let mut next = state.clone();
+            next.time -= wait + 1;
+            for (resource, stock) in next.stock.iter_mut().enumerate() {
+                *stock += state.robots[resource] * (wait + 1);
+                *stock -= self.blueprint.costs[robot][resource];
             }
+            next.robots[robot] += 1;
 
-            let mut new_state = *state;
-            new_state.increment();
-            new_states.push(new_state);
+            self.builds.push(Build {
+                robot,
+                minute: self.minutes - state.time + wait + 1,
+            });
+            self.run(next);
+            self.builds.pop();
+        }
+    }
+
+    /// Minutes of collecting before `robot` is affordable, if it ever is.
+    fn wait_for(&self, state: &State, robot: usize) -> Option<usize> {
+        self.blueprint.costs[robot]
+            .iter()
+            .zip(state.stock.iter().zip(&state.robots))
+            .map(
+                |(&cost, (&stock, &robots))| match (cost.saturating_sub(stock), robots) {
+                    (0, _) => Some(0),
+                    (_, 0) => None,
+                    (missing, robots) => Some(missing.div_ceil(robots)),
+                },
+            )
+            .try_fold(0, |wait, needed| needed.map(|needed| wait.max(needed)))
+    }
+}
+
+/// How one blueprint fared within the time limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Evaluation {
+    id: usize,
+    geodes: usize,
+    elapsed: Duration,
+}
+
+impl Evaluation {
+    fn quality(&self) -> usize {
+        self.id * self.geodes
+    }
+}
+
+/// Evaluations in blueprint order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Report {
+    minutes: usize,
+    evaluations: Vec<Evaluation>,
+}
+
+impl Report {
+    fn quality(&self) -> usize {
+        self.evaluations.iter().map(Evaluation::quality).sum()
+    }
+
+    fn geode_product(&self) -> usize {
+        self.evaluations.iter().map(|e| e.geodes).product()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} minutes", self.minutes)?;
+        writeln!(
+            f,
+            "{:>9} {:>6} {:>7} {:>10}",
+            "blueprint", "geodes", "quality", "time"
+        )?;
+        for evaluation in &self.evaluations {
+            writeln!(
+                f,
+                "{:>9} {:>6} {:>7} {:>10}",
+                evaluation.id,
+                evaluation.geodes,
+                evaluation.quality(),
+                format!("{:.2?}", evaluation.elapsed)
+            )?;
+        }
+        writeln!(
+            f,
+            "{:>9} {:>6} {:>7}",
+            "total",
+            self.geode_product(),
+            self.quality()
+        )
+    }
+}
+
+impl DaySolution {
+    fn evaluate(&self, blueprints: &[Blueprint], minutes: usize) -> Result<Report> {
+        let evaluations = blueprints
+            .par_iter()
+            .map(|blueprint| {
+                let goal = blueprint.resource(GOAL)?;
+                let started = Instant::now();
+                let geodes = blueprint.optimize(minutes, goal).collected;
+
+                Ok(Evaluation {
+                    id: blueprint.id,
+                    geodes,
+                    elapsed: started.elapsed(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-            new_states
+        Ok(Report {
+            minutes,
+            evaluations,
         })
-        .count();
+    }
+
+    /// Report tables for both parts, as evaluated by `part1` and `part2`.
+    fn report(&self, input: &str) -> Result<String> {
+        let blueprints = Blueprint::parse_all(input)?;
+
+        Ok(format!(
+            "{}\n{}",
+            self.evaluate(&blueprints, 24)?,
+            self.evaluate(&blueprints[..blueprints.len().min(3)], 32)?
+        ))
+    }
 
-        result
+    /// `report` lists every blueprint evaluated for both parts.
+    pub fn view(&self, input: &str, name: &str) -> Result<String> {
+        match name {
+            "report" => self.report(input),
+            _ => anyhow::bail!("Unknown view {name:?}, expected report"),
+        }
     }
 }
 
 impl Solution for DaySolution {
     fn part1(&self, input: &str) -> Result<Box<dyn Display>> {
-        let result: usize = input
-            .lines()
-            .map(|line| line.parse::<Blueprint>().unwrap())
-            .enumerate()
-            .map(|(id, blueprint)| self.solve(&blueprint, 24) * (id + 1))
-            .sum();
+        let report = self.evaluate(&Blueprint::parse_all(input)?, 24)?;
 
-        Ok(Box::new(result))
+        Ok(Box::new(report.quality()))
     }
 
     fn part2(&self, input: &str) -> Result<Box<dyn Display>> {
-        let result: usize = input
-            .lines()
-            .map(|line| line.parse::<Blueprint>().unwrap())
-            .map(|blueprint| self.solve(&blueprint, 32))
-            .take(3)
-            .product();
+        let blueprints = Blueprint::parse_all(input)?;
+        let report = self.evaluate(&blueprints[..blueprints.len().min(3)], 32)?;
 
-        Ok(Box::new(result))
+        Ok(Box::new(report.geode_product()))
     }
 }
 
@@ -177,4 +404,101 @@ mod test {
 
     day_test!(day19, Part1, "inputs/day19_demo.txt", "33");
     day_test!(day19, Part2, "inputs/day19_demo.txt", "3472");
+
+    #[test]
+    fn test_day19_build_order() {
+        use super::Blueprint;
+
+        let input = std::fs::read_to_string("inputs/day19_demo.txt").unwrap();
+        let blueprints = Blueprint::parse_all(&input).unwrap();
+
+        for (blueprint, expected) in blueprints.iter().zip([9, 12]) {
+            let geode = blueprint.resource("geode").unwrap();
+            let outcome = blueprint.optimize(24, geode);
+            assert_eq!(outcome.collected, expected);
+            assert_eq!(
+                blueprint.replay(24, geode, &outcome.builds).unwrap(),
+                expected
+            );
+            assert!(outcome.builds.iter().any(|build| build.robot == geode));
+        }
+
+        let geode = blueprints[0].resource("geode").unwrap();
+        let outcome = blueprints[0].optimize(32, geode);
+        assert_eq!(outcome.collected, 56);
+        assert_eq!(
+            blueprints[0].replay(32, geode, &outcome.builds).unwrap(),
+            56
+        );
+
+        let mut invalid = outcome.builds.clone();
+        invalid[0].minute = 1;
+        assert!(blueprints[0].replay(32, geode, &invalid).is_err());
+        assert_eq!(blueprints[0].optimize(1, geode).collected, 0);
+    }
+
+    #[test]
+    fn test_day19_parser() {
+        use super::Blueprint;
+
+        // Wrapped over several lines, as in the puzzle text, with ids out of order.
+        let input = "Blueprint 7:
+  Each ore robot costs 4 ore.
+  Each clay robot costs 2 ore.
+  Each obsidian robot costs 3 ore and 14 clay.
+  Each geode robot costs 2 ore and 7 obsidian.
+
+Blueprint 3: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsidian robot costs 3 ore and 8 clay. Each geode robot costs 3 ore and 12 obsidian.
+";
+        let blueprints = Blueprint::parse_all(input).unwrap();
+        assert_eq!(blueprints.iter().map(|b| b.id).collect::<Vec<_>>(), [7, 3]);
+        assert_eq!(
+            blueprints[0].resources,
+            ["ore", "clay", "obsidian", "geode"]
+        );
+        assert_eq!(blueprints[0].costs[3], [2, 0, 7, 0]);
+
+        // Costs may name resources in any order, and new kinds of resource.
+        let custom = Blueprint::parse_all(
+            "Blueprint 1: Each wood robot costs 1 wood. Each gem robot costs 3 wood and 1 wood.",
+        )
+        .unwrap();
+        assert_eq!(custom[0].costs, [[1, 0], [4, 0]]);
+        let gem = custom[0].resource("gem").unwrap();
+        assert_eq!(custom[0].optimize(6, gem).collected, 1);
+
+        assert!(Blueprint::parse_all("Blueprint 1: Each ore robot costs four ore.").is_err());
+        assert!(Blueprint::parse_all(
+            "Blueprint 1: Each ore robot costs 1 ore. Each ore robot costs 2 ore."
+        )
+        .is_err());
+        assert!(custom[0].resource("geode").is_err());
+    }
+
+    #[test]
+    fn test_day19_report() {
+        use super::{Blueprint, DaySolution};
+
+        let input = std::fs::read_to_string("inputs/day19_demo.txt").unwrap();
+        let blueprints = Blueprint::parse_all(&input).unwrap();
+        let solution = DaySolution {
+            filename: "inputs/day19_demo.txt",
+        };
+
+        let report = solution.evaluate(&blueprints, 24).unwrap();
+        let rows = report
+            .evaluations
+            .iter()
+            .map(|e| (e.id, e.geodes, e.quality()))
+            .collect::<Vec<_>>();
+        assert_eq!(rows, [(1, 9, 9), (2, 12, 24)]);
+        assert_eq!(report.quality(), 33);
+
+        let table = solution.report(&input).unwrap();
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "24 minutes");
+        assert_eq!(lines[1], "blueprint geodes quality       time");
+        assert!(lines[2].starts_with("        1      9       9 "));
+        assert_eq!(lines[4], "    total    108      33");
+    }
 }
@@ -2,8 +2,8 @@ use anyhow::Result;
 use aoc::{Runnable, Solution};
 use aoc_derive::Runner;
 use fxhash::FxHashMap as HashMap;
-use num::complex::Complex;
-use std::fmt::Display;
+use num::{BigInt, BigRational, Signed, Zero};
+use std::fmt::{Display, Formatter};
 
 #[derive(Runner)]
 #[aoc(file = "inputs/day21.txt")]
@@ -11,7 +11,10 @@ pub struct DaySolution {
     pub filename: &'static str,
 }
 
-type NumberType = Complex<f64>;
+const ROOT: &str = "root";
+const HUMAN: &str = "humn";
+
+type MonkeyId = u32;
 
 #[derive(parse_display::Display, parse_display::FromStr, Debug)]
 enum Operation {
@@ -24,7 +27,7 @@ enum Operation {
     #[display("{0} / {1}")]
     Divide(String, String),
     #[display("{0}")]
-    Number(NumberType),
+    Number(BigInt),
 }
 
 #[derive(parse_display::Display, parse_display::FromStr, Debug)]
@@ -34,56 +37,365 @@ struct Monkey {
     operation: Operation,
 }
 
-impl DaySolution {
-    fn parse(&self, input: &str) -> HashMap<String, Operation> {
-        input
-            .lines()
-            .map(|line| line.parse::<Monkey>().unwrap())
-            .map(|m: Monkey| (m.name, m.operation))
-            .collect::<HashMap<_, _>>()
+#[derive(parse_display::Display, Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    #[display("+")]
+    Add,
+    #[display("-")]
+    Subtract,
+    #[display("*")]
+    Multiply,
+    #[display("/")]
+    Divide,
+}
+
+impl Operator {
+    fn apply(self, a: &BigRational, b: &BigRational) -> Result<BigRational> {
+        Ok(match self {
+            Operator::Add => a + b,
+            Operator::Subtract => a - b,
+            Operator::Multiply => a * b,
+            Operator::Divide if b.is_zero() => anyhow::bail!("Division of {a} by zero"),
+            Operator::Divide => a / b,
+        })
+    }
+
+    fn precedence(self) -> u8 {
+        match self {
+            Operator::Add | Operator::Subtract => 1,
+            Operator::Multiply | Operator::Divide => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Job {
+    Number(BigInt),
+    Apply(Operator, [MonkeyId; 2]),
+}
+
+/// Monkeys interned by name, with each job pointing at the monkeys it waits for.
+#[derive(Debug, Default)]
+struct MonkeyGraph {
+    names: Vec<String>,
+    ids: HashMap<String, MonkeyId>,
+    jobs: Vec<Job>,
+    defined: Vec<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    New,
+    Open,
+    Done,
+}
+
+impl MonkeyGraph {
+    fn parse(input: &str) -> Result<Self> {
+        let mut graph = Self::default();
+
+        for (number, line) in input.lines().enumerate() {
+            let monkey = line
+                .parse::<Monkey>()
+                .map_err(|e| anyhow::anyhow!("Invalid monkey on line {}: {e}", number + 1))?;
+
+            let id = graph.intern(&monkey.name)? as usize;
+            if graph.defined[id] {
+                anyhow::bail!("Monkey {} is defined twice", monkey.name);
+            }
+            graph.defined[id] = true;
+            let (operator, a, b) = match &monkey.operation {
+                Operation::Number(n) => {
+                    graph.jobs[id] = Job::Number(n.clone());
+                    continue;
+                }
+                Operation::Add(a, b) => (Operator::Add, a, b),
+                Operation::Subtract(a, b) => (Operator::Subtract, a, b),
+                Operation::Multiply(a, b) => (Operator::Multiply, a, b),
+                Operation::Divide(a, b) => (Operator::Divide, a, b),
+            };
+            graph.jobs[id] = Job::Apply(operator, [graph.intern(a)?, graph.intern(b)?]);
+        }
+
+        if let Some(id) = graph.defined.iter().position(|&defined| !defined) {
+            let user = (0..graph.len()).find(|&user| {
+                graph
+                    .waits_for(user as MonkeyId)
+                    .contains(&(id as MonkeyId))
+            });
+            if let Some(user) = user {
+                anyhow::bail!(
+                    "Monkey {} is referenced by {} but never defined",
+                    graph.names[id],
+                    graph.names[user]
+                );
+            }
+            anyhow::bail!("Monkey {} is never defined", graph.names[id]);
+        }
+
+        Ok(graph)
+    }
+
+    fn intern(&mut self, name: &str) -> Result<MonkeyId> {
+        if let Some(&id) = self.ids.get(name) {
+            return Ok(id);
+        }
+
+        let id = MonkeyId::try_from(self.names.len())
+            .map_err(|_| anyhow::anyhow!("Too many monkeys to intern {name}"))?;
+        self.names.push(name.to_owned());
+        self.ids.insert(name.to_owned(), id);
+        self.jobs.push(Job::Number(BigInt::zero()));
+        self.defined.push(false);
+
+        Ok(id)
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    fn id(&self, name: &str) -> Result<MonkeyId> {
+        self.ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Unknown monkey {name}"))
+    }
+
+    fn waits_for(&self, id: MonkeyId) -> &[MonkeyId] {
+        match &self.jobs[id as usize] {
+            Job::Number(_) => &[],
+            Job::Apply(_, operands) => operands,
+        }
+    }
+
+    /// Monkeys `root` depends on, each after everything it waits for.
+    fn order(&self, root: MonkeyId) -> Result<Vec<MonkeyId>> {
+        let mut visits = vec![Visit::New; self.len()];
+        let mut order = vec![];
+        let mut path = vec![(root, 0)];
+        visits[root as usize] = Visit::Open;
+
+        while let Some((id, next)) = path.last_mut() {
+            let Some(&operand) = self.waits_for(*id).get(*next) else {
+                visits[*id as usize] = Visit::Done;
+                order.push(*id);
+                path.pop();
+                continue;
+            };
+            *next += 1;
+
+            match visits[operand as usize] {
+                Visit::New => {
+                    visits[operand as usize] = Visit::Open;
+                    path.push((operand, 0));
+                }
+                Visit::Open => {
+                    let start = path.iter().position(|&(id, _)| id == operand).unwrap();
+                    let cycle = path[start..]
+                        .iter()
+                        .chain([&(operand, 0)])
+                        .map(|&(id, _)| self.names[id as usize].as_str())
+                        .collect::<Vec<_>>();
+                    anyhow::bail!("Monkeys wait on each other: {}", cycle.join(" -> "));
+                }
+                Visit::Done => {}
+            }
+        }
+
+        Ok(order)
+    }
+
+    /// What `root` yells, folded down to a constant wherever `unknown` is not involved.
+    fn expr(&self, root: MonkeyId, unknown: Option<MonkeyId>) -> Result<Expr> {
+        let mut values = vec![None; self.len()];
+
+        for id in self.order(root)? {
+            let value = match &self.jobs[id as usize] {
+                _ if Some(id) == unknown => Expr::Unknown(self.names[id as usize].clone()),
+                Job::Number(n) => Expr::Constant(BigRational::from_integer(n.clone())),
+                Job::Apply(operator, [a, b]) => {
+                    let operand = |id: &MonkeyId| values[*id as usize].clone().unwrap();
+                    Expr::apply(*operator, operand(a), operand(b))?
+                }
+            };
+            values[id as usize] = Some(value);
+        }
+
+        Ok(values[root as usize].take().unwrap())
+    }
+
+    /// The two sides `root` compares, with the human left as the unknown.
+    fn equation(&self) -> Result<Equation> {
+        let (root, human) = (self.id(ROOT)?, self.id(HUMAN)?);
+        let [left, right] = match &self.jobs[root as usize] {
+            Job::Apply(_, operands) => *operands,
+            Job::Number(_) => anyhow::bail!("{ROOT} does not compare two monkeys"),
+        };
+
+        Ok(Equation {
+            left: self.expr(left, Some(human))?,
+            right: self.expr(right, Some(human))?,
+        })
+    }
+
+    /// Graphviz rendering with an edge from every monkey to the ones it waits for.
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph monkeys {\n");
+
+        for (id, name) in self.names.iter().enumerate() {
+            dot += &match &self.jobs[id] {
+                Job::Number(n) => format!("  {name} [label=\"{name}\\n{n}\", shape=box];\n"),
+                Job::Apply(operator, _) => format!("  {name} [label=\"{name}\\n{operator}\"];\n"),
+            };
+        }
+
+        for (id, name) in self.names.iter().enumerate() {
+            for &operand in self.waits_for(id as MonkeyId) {
+                dot += &format!("  {name} -> {};\n", self.names[operand as usize]);
+            }
+        }
+
+        dot + "}\n"
+    }
+}
+
+/// Expression over exact rationals; constant subexpressions are folded as it is built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Constant(BigRational),
+    Unknown(String),
+    Apply(Operator, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn apply(operator: Operator, a: Self, b: Self) -> Result<Self> {
+        match (&a, &b) {
+            (Expr::Constant(a), Expr::Constant(b)) => Ok(Expr::Constant(operator.apply(a, b)?)),
+            _ => Ok(Expr::Apply(operator, Box::new(a), Box::new(b))),
+        }
     }
 
-    fn compute(monkeys: &HashMap<String, Operation>, monkey: &str) -> NumberType {
-        match monkeys.get(monkey).unwrap() {
-            Operation::Number(n) => *n,
-            Operation::Add(a, b) => Self::compute(monkeys, a) + Self::compute(monkeys, b),
-            Operation::Subtract(a, b) => Self::compute(monkeys, a) - Self::compute(monkeys, b),
-            Operation::Multiply(a, b) => Self::compute(monkeys, a) * Self::compute(monkeys, b),
-            Operation::Divide(a, b) => Self::compute(monkeys, a) / Self::compute(monkeys, b),
+    fn constant(&self) -> Option<&BigRational> {
+        match self {
+            Expr::Constant(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Value of the unknown for which `self == target`, undoing one operation at a time.
+    fn solve(&self, target: BigRational) -> Result<BigRational> {
+        let Expr::Apply(operator, a, b) = self else {
+            return match self {
+                Expr::Unknown(_) => Ok(target),
+                _ => anyhow::bail!("The unknown does not appear in the equation"),
+            };
+        };
+
+        match (operator, a.constant(), b.constant()) {
+            (_, None, None) => anyhow::bail!("The unknown appears non-linearly"),
+            (Operator::Add, None, Some(b)) => a.solve(target - b),
+            (Operator::Add, Some(a), None) => b.solve(target - a),
+            (Operator::Subtract, None, Some(b)) => a.solve(target + b),
+            (Operator::Subtract, Some(a), None) => b.solve(a - target),
+            (Operator::Multiply, None, Some(b)) if !b.is_zero() => a.solve(target / b),
+            (Operator::Multiply, Some(a), None) if !a.is_zero() => b.solve(target / a),
+            (Operator::Multiply, _, _) => anyhow::bail!("The unknown is multiplied by zero"),
+            (Operator::Divide, None, Some(b)) if !b.is_zero() => a.solve(target * b),
+            (Operator::Divide, None, Some(_)) => anyhow::bail!("The unknown is divided by zero"),
+            (Operator::Divide, Some(_), None) => anyhow::bail!("The unknown appears in a divisor"),
+            (_, Some(_), Some(_)) => unreachable!("constants are folded"),
+        }
+    }
+
+    /// Whether `self` needs parentheses as an operand of `parent`.
+    fn needs_parentheses(&self, parent: Operator, right: bool) -> bool {
+        match self {
+            Expr::Constant(value) => value.is_negative() || !value.is_integer(),
+            Expr::Unknown(_) => false,
+            Expr::Apply(operator, _, _) => {
+                operator.precedence() < parent.precedence()
+                    || right
+                        && operator.precedence() == parent.precedence()
+                        && matches!(parent, Operator::Subtract | Operator::Divide)
+            }
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Constant(value) => write!(f, "{value}"),
+            Expr::Unknown(name) => write!(f, "{name}"),
+            Expr::Apply(operator, a, b) => {
+                for (operand, right) in [(a, false), (b, true)] {
+                    if right {
+                        write!(f, " {operator} ")?;
+                    }
+                    if operand.needs_parentheses(*operator, right) {
+                        write!(f, "({operand})")?;
+                    } else {
+                        write!(f, "{operand}")?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Equation {
+    left: Expr,
+    right: Expr,
+}
+
+impl Equation {
+    /// What the unknown has to be for both sides to match.
+    fn solve(&self) -> Result<BigRational> {
+        match (self.left.constant(), self.right.constant()) {
+            (Some(_), Some(_)) => anyhow::bail!("{HUMAN} does not affect {ROOT}"),
+            (None, None) => anyhow::bail!("{HUMAN} appears on both sides of {ROOT}"),
+            (Some(target), None) => self.right.solve(target.clone()),
+            (None, Some(target)) => self.left.solve(target.clone()),
+        }
+    }
+}
+
+impl Display for Equation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.left, self.right)
+    }
+}
+
+impl DaySolution {
+    /// `dot` renders the monkeys for Graphviz, `equation` the simplified test at the root.
+    pub fn view(&self, input: &str, name: &str) -> Result<String> {
+        let graph = MonkeyGraph::parse(input)?;
+
+        match name {
+            "dot" => Ok(graph.to_dot()),
+            "equation" => Ok(format!("{}\n", graph.equation()?)),
+            _ => anyhow::bail!("Unknown view {name:?}, expected dot or equation"),
         }
     }
 }
 
 impl Solution for DaySolution {
     fn part1(&self, input: &str) -> Result<Box<dyn Display>> {
-        let monkeys = self.parse(input);
-        let result = Self::compute(&monkeys, "root").re;
+        let graph = MonkeyGraph::parse(input)?;
+        let result = graph.expr(graph.id(ROOT)?, None)?;
 
         Ok(Box::new(result))
     }
 
     fn part2(&self, input: &str) -> Result<Box<dyn Display>> {
-        let mut monkeys = self.parse(input);
-        *monkeys.get_mut("humn").unwrap() = Operation::Number(Complex::new(0., 1.));
-
-        let (left, right) = match monkeys.get("root").unwrap() {
-            Operation::Add(a, b)
-            | Operation::Divide(a, b)
-            | Operation::Multiply(a, b)
-            | Operation::Subtract(a, b) => (a, b),
-            _ => unreachable!(),
-        };
+        let result = MonkeyGraph::parse(input)?.equation()?.solve()?;
 
-        let (result1, result2) = (
-            Self::compute(&monkeys, left),
-            Self::compute(&monkeys, right),
-        );
-
-        let result = if result1.im == 0. {
-            (result1.re - result2.re) / result2.im
-        } else {
-            (result2.re - result1.re) / result1.im
-        } as i64;
+        if !result.is_integer() {
+            anyhow::bail!("{HUMAN} would have to yell {result}");
+        }
 
         Ok(Box::new(result))
     }
@@ -97,4 +409,83 @@ mod test {
 
     day_test!(day21, Part1, "inputs/day21_demo.txt", "152");
     day_test!(day21, Part2, "inputs/day21_demo.txt", "301");
+
+    #[test]
+    fn test_day21_exact() {
+        use super::MonkeyGraph;
+
+        let solve = |input: &str| MonkeyGraph::parse(input)?.equation()?.solve();
+
+        // Far beyond the 53 bits an f64 keeps exactly.
+        let answer =
+            solve("root: huge + humn\nhuge: 123456789012345678901234567890\nhumn: 5").unwrap();
+        assert_eq!(answer.to_string(), "123456789012345678901234567890");
+        assert_eq!(
+            solve("root: humn + seven\nseven: 7\nhumn: 0").unwrap(),
+            solve("root: seven + humn\nseven: 7\nhumn: 0").unwrap()
+        );
+        assert_eq!(
+            solve("root: half + one\nhalf: humn / two\ntwo: 2\none: 1\nhumn: 0")
+                .unwrap()
+                .to_string(),
+            "2"
+        );
+
+        let error = |input: &str| solve(input).unwrap_err().to_string();
+        assert_eq!(
+            error("root: sq + one\nsq: humn * humn\none: 1\nhumn: 3"),
+            "The unknown appears non-linearly"
+        );
+        assert_eq!(
+            error("root: inv + one\ninv: one / humn\none: 1\nhumn: 3"),
+            "The unknown appears in a divisor"
+        );
+        assert_eq!(
+            error("root: div + one\ndiv: humn / zero\nzero: 0\none: 1\nhumn: 3"),
+            "The unknown is divided by zero"
+        );
+        assert_eq!(
+            error("root: humn + nobody\nhumn: 3"),
+            "Monkey nobody is referenced by root but never defined"
+        );
+        assert!(MonkeyGraph::parse("x: humn").is_err());
+    }
+
+    #[test]
+    fn test_day21_graph() {
+        use super::MonkeyGraph;
+
+        let input = std::fs::read_to_string("inputs/day21_demo.txt").unwrap();
+        let graph = MonkeyGraph::parse(&input).unwrap();
+        assert_eq!(graph.len(), 15);
+
+        let order = graph.order(graph.id("root").unwrap()).unwrap();
+        assert_eq!(order.len(), 15);
+        for (position, &id) in order.iter().enumerate() {
+            assert!(graph
+                .waits_for(id)
+                .iter()
+                .all(|operand| order[..position].contains(operand)));
+        }
+
+        assert_eq!(
+            graph.equation().unwrap().to_string(),
+            "(4 + 2 * (humn - 3)) / 4 = 150"
+        );
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph monkeys {\n  root [label=\"root\\n+\"];\n"));
+        assert!(dot.contains("  dbpl [label=\"dbpl\\n5\", shape=box];\n"));
+        assert_eq!(dot.matches(" -> ").count(), 14);
+
+        let cyclic = MonkeyGraph::parse("root: a + b\na: b * two\nb: a - two\ntwo: 2").unwrap();
+        assert_eq!(
+            cyclic
+                .expr(cyclic.id("root").unwrap(), None)
+                .unwrap_err()
+                .to_string(),
+            "Monkeys wait on each other: a -> b -> a"
+        );
+        assert!(MonkeyGraph::parse("root: 1\nroot: 2").is_err());
+    }
 }
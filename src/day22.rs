@@ -1,4 +1,8 @@
-use std::{collections::HashMap, fmt::Display, ops::Add};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{Display, Formatter},
+    ops::Add,
+};
 
 use anyhow::Result;
 use aoc::{Runnable, Solution};
@@ -12,43 +16,16 @@ use nom::{
 };
 use num::integer::Roots;
 
+use crate::geometry::{Point2, Point3};
+use crate::matrix::{Matrix, RaggedRows};
+
 #[derive(Runner)]
 #[aoc(file = "inputs/day22.txt")]
 pub struct DaySolution {
     pub filename: &'static str,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, parse_display::Display)]
-#[display("({x},{y})")]
-struct Position {
-    x: isize,
-    y: isize,
-}
-
-impl Position {
-    fn new(x: isize, y: isize) -> Self {
-        Self { x, y }
-    }
-
-    fn x_in_cube(&self, size: isize) -> isize {
-        self.x % size
-    }
-
-    fn y_in_cube(&self, size: isize) -> isize {
-        self.y % size
-    }
-}
-
-impl Add for Position {
-    type Output = Self;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        Position {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
-    }
-}
+type Position = Point2;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, parse_display::Display)]
 enum Direction {
@@ -81,16 +58,20 @@ impl Direction {
         }
     }
 
+    fn opposite(&self) -> Self {
+        self.turn_right().turn_right()
+    }
+
     fn offset(&self) -> Position {
         match self {
-            Direction::Up => Position::new(0, -1),
-            Direction::Right => Position::new(1, 0),
-            Direction::Down => Position::new(0, 1),
-            Direction::Left => Position::new(-1, 0),
+            Direction::Up => Position::xy(0, -1),
+            Direction::Right => Position::xy(1, 0),
+            Direction::Down => Position::xy(0, 1),
+            Direction::Left => Position::xy(-1, 0),
         }
     }
 
-    fn value(&self) -> isize {
+    fn value(&self) -> i64 {
         match self {
             Direction::Right => 0,
             Direction::Down => 1,
@@ -100,6 +81,13 @@ impl Direction {
     }
 }
 
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+    Direction::Up,
+];
+
 impl Add<Direction> for Position {
     type Output = Self;
 
@@ -130,20 +118,423 @@ impl Instruction {
     }
 }
 
-type Map = HashMap<Position, char>;
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Tile {
+    #[default]
+    Void,
+    Open,
+    Wall,
+}
+
+/// Where stepping off the board from a cell in a direction lands, and the facing afterwards.
+type Portals = HashMap<(Position, Direction), (Position, Direction)>;
+
+#[derive(Debug)]
+struct Board {
+    tiles: Matrix<Tile>,
+    rows: Vec<(i64, i64)>,
+    columns: Vec<(i64, i64)>,
+}
+
+impl Board {
+    fn parse(input: &str) -> Result<Self> {
+        let (tiles, _) = Matrix::parse_grid(input, &[], RaggedRows::Pad, |c, (x, y)| match c {
+            ' ' => Ok(Tile::Void),
+            '.' => Ok(Tile::Open),
+            '#' => Ok(Tile::Wall),
+            c => Err(anyhow::anyhow!(
+                "Invalid tile {c:?} at line {}, column {}",
+                y + 1,
+                x + 1
+            )),
+        })?;
+
+        let extent = |cells: Vec<&Tile>| {
+            let first = cells.iter().position(|&&tile| tile != Tile::Void)?;
+            let last = cells.iter().rposition(|&&tile| tile != Tile::Void)?;
+            Some((first as i64, last as i64))
+        };
+        let rows = tiles
+            .rows()
+            .map(|row| extent(row.iter().collect()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| anyhow::anyhow!("Board has an empty row"))?;
+        let columns = tiles
+            .columns()
+            .map(|column| extent(column.collect()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| anyhow::anyhow!("Board has an empty column"))?;
+
+        Ok(Self {
+            tiles,
+            rows,
+            columns,
+        })
+    }
+
+    fn get(&self, position: Position) -> Tile {
+        self.tiles
+            .get(position.x(), position.y())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        self.tiles
+            .iter()
+            .filter(|&(&tile, _)| tile != Tile::Void)
+            .map(|(_, (x, y))| Position::xy(x as i64, y as i64))
+    }
+
+    fn start(&self) -> Option<Position> {
+        self.positions().find(|&p| self.get(p) == Tile::Open)
+    }
+
+    /// Every way of stepping off the board.
+    fn exits(&self) -> impl Iterator<Item = (Position, Direction)> + '_ {
+        self.positions()
+            .flat_map(|p| DIRECTIONS.map(|direction| (p, direction)))
+            .filter(|&(p, direction)| self.get(p + direction) == Tile::Void)
+    }
+
+    /// The board with every traced step drawn as the facing it left the cell with.
+    fn traced(&self, trace: &[Step]) -> Matrix<char> {
+        let mut rendered = Matrix {
+            width: self.tiles.width,
+            height: self.tiles.height,
+            data: self
+                .tiles
+                .iter()
+                .map(|(tile, _)| match tile {
+                    Tile::Void => ' ',
+                    Tile::Open => '.',
+                    Tile::Wall => '#',
+                })
+                .collect(),
+        };
+
+        for step in trace {
+            let glyph = step.facing.to_string().chars().next().unwrap();
+            rendered.set(step.position.x(), step.position.y(), glyph);
+        }
+
+        rendered
+    }
+
+    fn flat_portals(&self) -> Portals {
+        self.exits()
+            .map(|(p, direction)| {
+                let (row, column) = (self.rows[p.y() as usize], self.columns[p.x() as usize]);
+                let target = match direction {
+                    Direction::Right => Position::xy(row.0, p.y()),
+                    Direction::Left => Position::xy(row.1, p.y()),
+                    Direction::Down => Position::xy(p.x(), column.0),
+                    Direction::Up => Position::xy(p.x(), column.1),
+                };
+
+                ((p, direction), (target, direction))
+            })
+            .collect()
+    }
+}
+
+/// A face of the folded cube: its tile in the net and where the net's axes point in 3D.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Face {
+    tile: Position,
+    normal: Point3,
+    right: Point3,
+    down: Point3,
+}
+
+impl Face {
+    fn axis(&self, direction: Direction) -> Point3 {
+        match direction {
+            Direction::Right => self.right,
+            Direction::Down => self.down,
+            Direction::Left => -self.right,
+            Direction::Up => -self.down,
+        }
+    }
+
+    /// The neighbouring tile in `direction`, folded over the shared edge.
+    fn folded(&self, direction: Direction) -> Self {
+        let mut face = Face {
+            tile: self.tile + direction,
+            normal: self.axis(direction),
+            ..*self
+        };
+
+        match direction {
+            Direction::Right => face.right = -self.normal,
+            Direction::Down => face.down = -self.normal,
+            Direction::Left => face.right = self.normal,
+            Direction::Up => face.down = self.normal,
+        }
+
+        face
+    }
+}
+
+/// Where walking off a face edge leads: the face entered, the new facing, and whether
+/// the order of cells along the edge is reversed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Edge {
+    from: (usize, Direction),
+    to: (usize, Direction),
+    flipped: bool,
+}
+
+#[derive(Debug)]
+struct CubeNet {
+    size: i64,
+    faces: Vec<Face>,
+    edges: Vec<Edge>,
+}
+
+impl CubeNet {
+    fn fold(board: &Board) -> Result<Self> {
+        let cells = board.positions().count();
+        let size = (cells / 6).sqrt();
+        if size == 0 || size * size * 6 != cells {
+            anyhow::bail!("{cells} tiles cannot cover the faces of a cube");
+        }
+
+        let size = size as i64;
+        let tiles = board
+            .positions()
+            .map(|p| Position::xy(p.x() / size, p.y() / size))
+            .collect::<HashSet<_>>();
+        let first = *tiles
+            .iter()
+            .min_by_key(|tile| (tile.y(), tile.x()))
+            .unwrap();
+
+        let mut faces = vec![Face {
+            tile: first,
+            normal: Point3::xyz(0, 0, -1),
+            right: Point3::xyz(1, 0, 0),
+            down: Point3::xyz(0, 1, 0),
+        }];
+        let mut queue = VecDeque::from([faces[0]]);
+
+        while let Some(face) = queue.pop_front() {
+            for direction in DIRECTIONS {
+                let next = face.folded(direction);
+                if tiles.contains(&next.tile) && faces.iter().all(|f| f.tile != next.tile) {
+                    faces.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        if tiles.len() != 6 || faces.len() != 6 {
+            anyhow::bail!(
+                "Expected 6 connected faces of size {size}, found {} tiles with {} connected",
+                tiles.len(),
+                faces.len()
+            );
+        }
+        if faces.iter().map(|f| f.normal).collect::<HashSet<_>>().len() != 6 {
+            anyhow::bail!("Faces overlap when the net is folded");
+        }
+
+        faces.sort_by_key(|face| (face.tile.y(), face.tile.x()));
+        let mut net = Self {
+            size,
+            faces,
+            edges: vec![],
+        };
+        net.edges = net.stitch()?;
+
+        Ok(net)
+    }
+
+    fn face_of(&self, position: Position) -> Option<usize> {
+        let tile = Position::xy(
+            position.x().div_euclid(self.size),
+            position.y().div_euclid(self.size),
+        );
+        self.faces.iter().position(|f| f.tile == tile)
+    }
+
+    /// Position and facing after stepping off the net at `position` towards `direction`,
+    /// or `None` when `position` is not on the net.
+    fn wrap(&self, position: Position, direction: Direction) -> Option<(Position, Direction)> {
+        let face = &self.faces[self.face_of(position)?];
+        let size = self.size;
+        let (i, j) = (position.x() % size, position.y() % size);
+
+        // Cell centres in 3D, on a cube of side 2 * size centred on the origin.
+        let centre =
+            face.normal * size + face.right * (2 * i + 1 - size) + face.down * (2 * j + 1 - size);
+        let axis = face.axis(direction);
+        let target = centre + axis - face.normal;
+
+        let to = self.faces.iter().find(|f| f.normal == axis)?;
+        let facing = DIRECTIONS
+            .into_iter()
+            .find(|&d| to.axis(d) == face.normal)?
+            .opposite();
+
+        Some((
+            Position::xy(
+                to.tile.x() * size + (target.dot(&to.right) + size - 1) / 2,
+                to.tile.y() * size + (target.dot(&to.down) + size - 1) / 2,
+            ),
+            facing,
+        ))
+    }
+
+    fn portals(&self, board: &Board) -> Result<Portals> {
+        board
+            .exits()
+            .map(|(p, direction)| {
+                let target = self
+                    .wrap(p, direction)
+                    .ok_or_else(|| anyhow::anyhow!("{p} is not on the cube net"))?;
+                Ok(((p, direction), target))
+            })
+            .collect()
+    }
+
+    /// Each face of a traced board on its own, in face order.
+    fn unfolded(&self, traced: &Matrix<char>) -> String {
+        let size = self.size as usize;
+
+        self.faces
+            .iter()
+            .enumerate()
+            .map(|(index, face)| {
+                let (x, y) = (face.tile.x() as usize * size, face.tile.y() as usize * size);
+                let view = traced.crop(x, y, size, size).unwrap();
+                format!("Face {index} at {}\n{view}", face.tile)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Where each face edge leads once the net is folded.
+    fn stitch(&self) -> Result<Vec<Edge>> {
+        let last = self.size - 1;
+
+        (0..self.faces.len())
+            .flat_map(|face| DIRECTIONS.map(|direction| (face, direction)))
+            .map(|(face, direction)| {
+                let origin = Position::xy(
+                    self.faces[face].tile.x() * self.size,
+                    self.faces[face].tile.y() * self.size,
+                );
+                let [first, second] = [0, last].map(|t| {
+                    let cell = match direction {
+                        Direction::Right => Position::xy(last, t),
+                        Direction::Down => Position::xy(t, last),
+                        Direction::Left => Position::xy(0, t),
+                        Direction::Up => Position::xy(t, 0),
+                    };
+                    self.wrap(origin + cell, direction)
+                });
+                let (Some(first), Some(second)) = (first, second) else {
+                    anyhow::bail!("Face {face} has no neighbour towards {direction}");
+                };
+
+                let along = |(p, facing): (Position, Direction)| match facing {
+                    Direction::Up | Direction::Down => p.x(),
+                    Direction::Left | Direction::Right => p.y(),
+                };
+
+                let to = self.face_of(first.0).ok_or_else(|| {
+                    anyhow::anyhow!("Face {face} {direction} leads off the net to {}", first.0)
+                })?;
+
+                Ok(Edge {
+                    from: (face, direction),
+                    to: (to, first.1),
+                    flipped: along(first) > along(second),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Display for CubeNet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let width = self
+            .faces
+            .iter()
+            .map(|face| face.tile.x())
+            .max()
+            .unwrap_or(0);
+        let height = self
+            .faces
+            .iter()
+            .map(|face| face.tile.y())
+            .max()
+            .unwrap_or(0);
+
+        for y in 0..=height {
+            for x in 0..=width {
+                match self
+                    .faces
+                    .iter()
+                    .position(|face| face.tile == Position::xy(x, y))
+                {
+                    Some(index) => write!(f, "{index}")?,
+                    None => write!(f, ".")?,
+                }
+            }
+            writeln!(f)?;
+        }
+
+        for edge in &self.edges {
+            writeln!(
+                f,
+                "{}{} -> {}{}{}",
+                edge.from.0,
+                edge.from.1,
+                edge.to.0,
+                edge.to.1,
+                if edge.flipped { " flipped" } else { "" }
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The person's state after a move or turn made while following `instruction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Step {
+    position: Position,
+    facing: Direction,
+    instruction: usize,
+}
 
 #[derive(Debug)]
 struct Person {
     position: Position,
     direction: Direction,
+    trace: Vec<Step>,
 }
 
 impl Person {
     fn new(position: Position) -> Self {
-        Self {
+        let mut person = Self {
             position,
             direction: Direction::Right,
-        }
+            trace: vec![],
+        };
+        person.record(0);
+
+        person
+    }
+
+    fn record(&mut self, instruction: usize) {
+        self.trace.push(Step {
+            position: self.position,
+            facing: self.direction,
+            instruction,
+        });
     }
 
     fn turn(&mut self, direction: char) {
@@ -154,264 +545,133 @@ impl Person {
         };
     }
 
-    fn walk(&mut self, map: &Map, steps: usize) {
+    fn walk(
+        &mut self,
+        board: &Board,
+        portals: &Portals,
+        steps: usize,
+        instruction: usize,
+    ) -> Result<()> {
         for _ in 0..steps {
-            let new_position = self.position + self.direction;
-
-            match map.get(&new_position) {
-                Some('.') => self.position = new_position,
-                Some('#') => break,
-                None => match self.direction {
-                    Direction::Down => {
-                        let new_position = map
-                            .keys()
-                            .filter(|p| p.x == self.position.x)
-                            .min_by_key(|p| p.y)
-                            .unwrap();
-
-                        if new_position != &self.position && map.get(new_position) == Some(&'.') {
-                            self.position = *new_position;
-                        }
-                    }
-                    Direction::Right => {
-                        let new_position = map
-                            .keys()
-                            .filter(|p| p.y == self.position.y)
-                            .min_by_key(|p| p.x)
-                            .unwrap();
-
-                        if new_position != &self.position && map.get(new_position) == Some(&'.') {
-                            self.position = *new_position;
-                        }
-                    }
-                    Direction::Up => {
-                        let new_position = map
-                            .keys()
-                            .filter(|p| p.x == self.position.x)
-                            .max_by_key(|p| p.y)
-                            .unwrap();
-
-                        if new_position != &self.position && map.get(new_position) == Some(&'.') {
-                            self.position = *new_position;
-                        }
-                    }
-                    Direction::Left => {
-                        let new_position = map
-                            .keys()
-                            .filter(|p| p.y == self.position.y)
-                            .max_by_key(|p| p.x)
-                            .unwrap();
-
-                        if new_position != &self.position && map.get(new_position) == Some(&'.') {
-                            self.position = *new_position;
-                        }
-                    }
-                },
-                _ => unreachable!(),
+            let (position, direction) = portals
+                .get(&(self.position, self.direction))
+                .copied()
+                .unwrap_or((self.position + self.direction, self.direction));
+
+            match board.get(position) {
+                Tile::Open => {
+                    self.position = position;
+                    self.direction = direction;
+                    self.record(instruction);
+                }
+                Tile::Wall => break,
+                Tile::Void => anyhow::bail!("Walked off the board at {position}"),
             }
         }
-    }
 
-    fn follow(&mut self, map: &Map, instruction: &Instruction) {
-        match instruction {
-            Instruction::Steps(steps) => self.walk(map, *steps),
-            Instruction::TurnLeft => self.turn('L'),
-            Instruction::TurnRight => self.turn('R'),
-        }
+        Ok(())
     }
 
-    fn follow_cube(&mut self, map: &Map, instruction: &Instruction, cube_size: isize) {
+    fn follow(
+        &mut self,
+        board: &Board,
+        portals: &Portals,
+        (index, instruction): (usize, &Instruction),
+    ) -> Result<()> {
         match instruction {
-            Instruction::Steps(steps) => self.walk_cube(map, *steps, cube_size),
-            Instruction::TurnLeft => self.turn('L'),
-            Instruction::TurnRight => self.turn('R'),
-        }
-    }
-
-    fn walk_cube(&mut self, map: &Map, steps: usize, size: isize) {
-        for _ in 0..steps {
-            let new_position = self.position + self.direction;
-            match map.get(&new_position) {
-                Some('.') => self.position = new_position,
-                Some('#') => break,
-                None => match self.direction {
-                    Direction::Up => {
-                        if self.position.x / size == 0 {
-                            let new_position =
-                                Position::new(size, self.position.x_in_cube(size) + size);
-
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Right;
-                            }
-                        } else if self.position.x / size == 1 {
-                            let new_position =
-                                Position::new(0, self.position.x_in_cube(size) + 3 * size);
-
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Right;
-                            }
-                        } else if self.position.x / size == 2 {
-                            let new_position =
-                                Position::new(self.position.x_in_cube(size), 4 * size - 1);
-
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                            }
-                        }
-                    }
-                    Direction::Down => {
-                        if self.position.x / size == 0 {
-                            let new_position = Position::new(size * 2 + self.position.x, 0);
-
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                            }
-                        } else if self.position.x / size == 1 {
-                            let new_position =
-                                Position::new(size - 1, self.position.x_in_cube(size) + 3 * size);
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Left;
-                            }
-                        } else if self.position.x / size == 2 {
-                            let new_position =
-                                Position::new(size * 2 - 1, self.position.x_in_cube(size) + size);
-
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Left;
-                            }
-                        }
-                    }
-                    Direction::Right => {
-                        if self.position.y / size == 0 {
-                            let new_position = Position::new(
-                                size * 2 - 1,
-                                size * 3 - self.position.y_in_cube(size) - 1,
-                            );
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Left;
-                            }
-                        } else if self.position.y / size == 1 {
-                            let new_position =
-                                Position::new(size * 2 + self.position.y_in_cube(size), size - 1);
-
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Up;
-                            }
-                        } else if self.position.y / size == 2 {
-                            let new_position = Position::new(
-                                size * 3 - 1,
-                                size - self.position.y_in_cube(size) - 1,
-                            );
-
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Left;
-                            }
-                        } else if self.position.y / size == 3 {
-                            let new_position =
-                                Position::new(size + self.position.y_in_cube(size), size * 3 - 1);
-
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Up;
-                            }
-                        }
-                    }
-                    Direction::Left => {
-                        if self.position.y / size == 0 {
-                            let new_position =
-                                Position::new(0, size * 3 - self.position.y_in_cube(size) - 1);
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Right;
-                            }
-                        } else if self.position.y / size == 1 {
-                            let new_position =
-                                Position::new(self.position.y_in_cube(size), size * 2);
-
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Down;
-                            }
-                        } else if self.position.y / size == 2 {
-                            let new_position =
-                                Position::new(size, size - self.position.y_in_cube(size) - 1);
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Right;
-                            }
-                        } else if self.position.y / size == 3 {
-                            let new_position =
-                                Position::new(self.position.y_in_cube(size) + size, 0);
-
-                            if map.get(&new_position) == Some(&'.') {
-                                self.position = new_position;
-                                self.direction = Direction::Down;
-                            }
-                        }
-                    }
-                },
-                _ => unreachable!(),
+            Instruction::Steps(steps) => self.walk(board, portals, *steps, index)?,
+            Instruction::TurnLeft => {
+                self.turn('L');
+                self.record(index);
+            }
+            Instruction::TurnRight => {
+                self.turn('R');
+                self.record(index);
             }
         }
+
+        Ok(())
     }
 
-    fn password(&self) -> isize {
-        (self.position.y + 1) * 1000 + (self.position.x + 1) * 4 + self.direction.value()
+    fn password(&self) -> i64 {
+        (self.position.y() + 1) * 1000 + (self.position.x() + 1) * 4 + self.direction.value()
     }
 }
 
 impl DaySolution {
-    fn parse(&self, input: &str) -> Result<(Map, Vec<Instruction>)> {
-        let (map, instructions) = input.split_once("\n\n").unwrap();
-        let (_, instructions) = Instruction::parse(instructions).unwrap();
-
-        let map = Map::from_iter(map.lines().enumerate().flat_map(|(y, line)| {
-            line.chars().enumerate().filter_map(move |(x, c)| {
-                if c == ' ' {
-                    return None;
-                }
+    fn parse(&self, input: &str) -> Result<(Board, Vec<Instruction>)> {
+        let (board, instructions) = input
+            .split_once("\n\n")
+            .ok_or_else(|| anyhow::anyhow!("Missing path description"))?;
+        let (_, instructions) = Instruction::parse(instructions.trim())
+            .map_err(|e| anyhow::anyhow!("Invalid path description: {e}"))?;
+
+        Ok((Board::parse(board)?, instructions))
+    }
 
-                Some((Position::new(x as isize, y as isize), c))
-            })
-        }));
+    fn walk(
+        &self,
+        board: &Board,
+        portals: &Portals,
+        instructions: &[Instruction],
+    ) -> Result<Person> {
+        let start = board
+            .start()
+            .ok_or_else(|| anyhow::anyhow!("Board has no open tile"))?;
+        let mut person = Person::new(start);
+
+        for instruction in instructions.iter().enumerate() {
+            person.follow(board, portals, instruction)?;
+        }
 
-        Ok((map, instructions))
+        Ok(person)
     }
-}
 
-impl Solution for DaySolution {
-    fn part1(&self, input: &str) -> Result<Box<dyn Display>> {
-        let (map, instructions) = self.parse(input)?;
+    /// Renders the walk over the flat board, or over the cube followed by each face.
+    fn trace(&self, input: &str, cube: bool) -> Result<String> {
+        let (board, instructions) = self.parse(input)?;
 
-        let position = map.keys().min_by_key(|p| (p.y, p.x)).unwrap();
-        let mut person = Person::new(*position);
+        if cube {
+            let net = CubeNet::fold(&board)?;
+            let person = self.walk(&board, &net.portals(&board)?, &instructions)?;
+            let traced = board.traced(&person.trace);
 
-        for instruction in instructions {
-            person.follow(&map, &instruction);
+            Ok(format!("{traced}\n{net}\n{}", net.unfolded(&traced)))
+        } else {
+            let person = self.walk(&board, &board.flat_portals(), &instructions)?;
+
+            Ok(board.traced(&person.trace).to_string())
         }
+    }
 
-        Ok(Box::new(person.password()))
+    /// `trace` draws the walk on the flat board, `cube` the walk around the cube along with
+    /// the folded net and its stitched edges.
+    pub fn view(&self, input: &str, name: &str) -> Result<String> {
+        match name {
+            "trace" => self.trace(input, false),
+            "cube" => self.trace(input, true),
+            _ => anyhow::bail!("Unknown view {name:?}, expected trace or cube"),
+        }
     }
+}
 
-    fn part2(&self, input: &str) -> Result<Box<dyn Display>> {
-        let (map, instructions) = self.parse(input)?;
-        let size = (map.values().count() / 6).sqrt() as isize;
+impl Solution for DaySolution {
+    fn part1(&self, input: &str) -> Result<Box<dyn Display>> {
+        let (board, instructions) = self.parse(input)?;
+        let portals = board.flat_portals();
 
-        let position = map.keys().min_by_key(|p| (p.y, p.x)).unwrap();
-        let mut person = Person::new(*position);
+        Ok(Box::new(
+            self.walk(&board, &portals, &instructions)?.password(),
+        ))
+    }
 
-        for instruction in instructions {
-            person.follow_cube(&map, &instruction, size);
-        }
+    fn part2(&self, input: &str) -> Result<Box<dyn Display>> {
+        let (board, instructions) = self.parse(input)?;
+        let portals = CubeNet::fold(&board)?.portals(&board)?;
 
-        Ok(Box::new(person.password()))
+        Ok(Box::new(
+            self.walk(&board, &portals, &instructions)?.password(),
+        ))
     }
 }
 
@@ -422,5 +682,175 @@ mod test {
     use paste::paste;
 
     day_test!(day22, Part1, "inputs/day22_demo.txt", "6032");
-    day_test!(day22, Part2, "inputs/day22.txt", "129339");
+    day_test!(day22, Part2, "inputs/day22_demo.txt", "5031");
+
+    #[test]
+    fn test_day22_part2_input() -> anyhow::Result<()> {
+        let day = day22::DaySolution {
+            filename: "inputs/day22.txt",
+        };
+        assert_eq!(day.run(aoc::SolutionPart::Part2)?.to_string(), "129339");
+        Ok(())
+    }
+
+    #[test]
+    fn test_day22_cube_nets() {
+        use super::{Board, CubeNet, Direction, Tile};
+        use itertools::Itertools;
+
+        let nets = [
+            "#...\n####\n#...",
+            "#...\n####\n.#..",
+            "#...\n####\n..#.",
+            "#...\n####\n...#",
+            ".#..\n####\n.#..",
+            ".#..\n####\n..#.",
+            "##..\n.###\n.#..",
+            "##..\n.###\n..#.",
+            "##..\n.###\n...#",
+            "##..\n.##.\n..##",
+            "###..\n..###",
+        ];
+        let unfold = |net: &str| {
+            let board = net
+                .lines()
+                .flat_map(|line| {
+                    let row = line
+                        .chars()
+                        .map(|c| if c == '#' { "..." } else { "   " })
+                        .collect::<String>();
+                    [row.clone(), row.clone(), row]
+                })
+                .join("\n");
+            Board::parse(&board).unwrap()
+        };
+
+        for net in nets {
+            let board = unfold(net);
+            let cube = CubeNet::fold(&board).unwrap();
+
+            // Walking off any edge cell and straight back must return to where we started.
+            for (position, direction) in board.exits() {
+                let (entered, facing) = cube.wrap(position, direction).unwrap();
+                assert_ne!(
+                    board.get(entered),
+                    Tile::Void,
+                    "{net}: {position} {direction}"
+                );
+                assert_eq!(
+                    cube.wrap(entered, facing.opposite()),
+                    Some((position, direction.opposite())),
+                    "{net}"
+                );
+            }
+
+            let edges = &cube.edges;
+            for face in 0..6 {
+                let neighbours = edges
+                    .iter()
+                    .filter(|edge| edge.from.0 == face)
+                    .map(|edge| edge.to.0)
+                    .collect::<std::collections::HashSet<_>>();
+                assert_eq!(neighbours.len(), 4, "{net}\n{cube}");
+                assert!(!neighbours.contains(&face));
+            }
+        }
+
+        assert!(CubeNet::fold(&unfold("######")).is_err());
+        assert!(CubeNet::fold(&unfold("#...\n####")).is_err());
+
+        let demo = std::fs::read_to_string("inputs/day22_demo.txt").unwrap();
+        let (board, _) = day22::DaySolution::default().parse(&demo).unwrap();
+        let cube = CubeNet::fold(&board).unwrap();
+        assert!(cube.to_string().starts_with("..0.\n123.\n..45\n"));
+        assert!(cube.edges.contains(&super::Edge {
+            from: (0, Direction::Up),
+            to: (1, Direction::Down),
+            flipped: true,
+        }));
+        assert_eq!(cube.face_of(super::Position::xy(0, 0)), None);
+        assert_eq!(cube.wrap(super::Position::xy(-1, 0), Direction::Left), None);
+
+        let error = CubeNet::fold(&unfold("###.\n...###")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Expected 6 connected faces of size 3, found 6 tiles with 3 connected"
+        );
+    }
+
+    #[test]
+    fn test_day22_board() {
+        use super::{Direction, Position, Tile};
+
+        let demo = std::fs::read_to_string("inputs/day22_demo.txt").unwrap();
+        let (board, instructions) = day22::DaySolution::default().parse(&demo).unwrap();
+        assert_eq!(instructions.len(), 13);
+        assert_eq!(board.rows[0], (8, 11));
+        assert_eq!(board.rows[4], (0, 11));
+        assert_eq!(board.columns[0], (4, 7));
+        assert_eq!(board.start(), Some(Position::xy(8, 0)));
+        assert_eq!(board.get(Position::xy(3, 0)), Tile::Void);
+
+        let portals = board.flat_portals();
+        assert_eq!(
+            portals[&(Position::xy(5, 7), Direction::Down)],
+            (Position::xy(5, 4), Direction::Down)
+        );
+        assert_eq!(
+            portals[&(Position::xy(8, 0), Direction::Left)],
+            (Position::xy(11, 0), Direction::Left)
+        );
+    }
+
+    #[test]
+    fn test_day22_trace() {
+        let demo = std::fs::read_to_string("inputs/day22_demo.txt").unwrap();
+        let day = day22::DaySolution::default();
+
+        let flat = day.trace(&demo, false).unwrap();
+        let flat = flat.lines().map(str::trim_end).collect::<Vec<_>>();
+        assert_eq!(
+            flat,
+            [
+                "        >>V#",
+                "        .#V.",
+                "        #.V.",
+                "        ..V.",
+                "...#...V..V#",
+                ">>>V...>#.>>",
+                "..#V...#....",
+                "...>>>>V..#.",
+                "        ...#....",
+                "        .....#..",
+                "        .#......",
+                "        ......#.",
+            ]
+        );
+
+        let cube = day.trace(&demo, true).unwrap();
+        assert!(cube.contains("        .#V<<<<.\n"));
+        assert!(cube.contains("Face 5 at (3,2)\n..V.\n.#V.\n<<<.\n..#.\n"));
+        assert_eq!(cube.matches("Face ").count(), 6);
+        assert!(cube.contains("\n..0.\n123.\n..45\n0> -> "));
+        assert!(cube.contains("\n0^ -> 1V flipped\n"));
+
+        let (board, instructions) = day.parse(&demo).unwrap();
+        let person = day
+            .walk(&board, &board.flat_portals(), &instructions)
+            .unwrap();
+        let last = person.trace.last().unwrap();
+        assert!(day
+            .walk(&board, &Default::default(), &instructions)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Walked off the board at "));
+        assert_eq!(
+            (last.position, last.facing),
+            (person.position, person.direction)
+        );
+        assert!(person
+            .trace
+            .windows(2)
+            .all(|pair| pair[0].instruction <= pair[1].instruction));
+    }
 }
@@ -1,12 +1,11 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fmt::Display,
-};
+use std::{collections::HashMap, fmt::Display};
 
 use anyhow::Result;
 use aoc::{Runnable, Solution};
 use aoc_derive::Runner;
-use pathfinding::matrix::directions::DIRECTIONS_8;
+
+use crate::automaton::{Automaton, Rule};
+use crate::matrix::{Coord, Matrix, SparseGrid, MATRIX_NEIGHBOURS_8};
 
 #[derive(Runner)]
 #[aoc(file = "inputs/day23.txt")]
@@ -14,30 +13,7 @@ pub struct DaySolution {
     pub filename: &'static str,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, parse_display::Display)]
-#[display("({x},{y})")]
-struct Position {
-    x: isize,
-    y: isize,
-}
-
-impl std::ops::Add<(isize, isize)> for Position {
-    type Output = Self;
-
-    fn add(self, (x, y): (isize, isize)) -> Self::Output {
-        Self {
-            x: self.x + x,
-            y: self.y + y,
-        }
-    }
-}
-
-type Map = HashSet<Position>;
-
-trait HasBounds {
-    fn bounds(&self) -> (Position, Position);
-    fn count_empty(&self) -> usize;
-}
+type Map = SparseGrid<char>;
 
 #[derive(Debug)]
 enum Direction {
@@ -48,7 +24,7 @@ enum Direction {
 }
 
 impl Direction {
-    fn to_offsets(&self) -> Vec<(isize, isize)> {
+    fn to_offsets(&self) -> Vec<(i32, i32)> {
         match self {
             // N, NE, NW
             Direction::North => vec![(-1, -1), (0, -1), (1, -1)],
@@ -61,7 +37,7 @@ impl Direction {
         }
     }
 
-    fn to_offset(&self) -> (isize, isize) {
+    fn to_offset(&self) -> (i32, i32) {
         match self {
             Direction::North => (0, -1),
             Direction::South => (0, 1),
@@ -71,172 +47,72 @@ impl Direction {
     }
 }
 
-impl HasBounds for Map {
-    fn bounds(&self) -> (Position, Position) {
-        let (mut min_x, mut min_y) = (isize::MAX, isize::MAX);
-        let (mut max_x, mut max_y) = (isize::MIN, isize::MIN);
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::West,
+    Direction::East,
+];
+
+struct Elves;
+
+impl Rule<Map> for Elves {
+    fn step(&self, current: &Map, next: &mut Map, generation: usize) {
+        let mut proposed: HashMap<Coord, Vec<Coord>> = HashMap::new();
+
+        for pos in current.coords() {
+            let is_alone = MATRIX_NEIGHBOURS_8.iter().all(|&offset| {
+                pos.checked_offset(offset)
+                    .is_none_or(|pos| !current.contains(pos))
+            });
+
+            let target = (0..DIRECTIONS.len())
+                .map(|i| &DIRECTIONS[(generation + i) % DIRECTIONS.len()])
+                .find(|dir| {
+                    dir.to_offsets().iter().all(|&offset| {
+                        pos.checked_offset(offset)
+                            .is_none_or(|pos| !current.contains(pos))
+                    })
+                })
+                .filter(|_| !is_alone);
 
-        for pos in self {
-            min_x = min_x.min(pos.x);
-            min_y = min_y.min(pos.y);
-            max_x = max_x.max(pos.x);
-            max_y = max_y.max(pos.y);
+            match target.and_then(|dir| pos.checked_offset(dir.to_offset())) {
+                Some(target) => proposed.entry(target).or_default().push(pos),
+                None => _ = next.set_at(pos, '#'),
+            }
         }
 
-        (
-            Position { x: min_x, y: min_y },
-            Position { x: max_x, y: max_y },
-        )
-    }
-
-    fn count_empty(&self) -> usize {
-        let (min, max) = self.bounds();
-        (min.x..=max.x)
-            .flat_map(|x| (min.y..=max.y).map(move |y| !self.contains(&Position { x, y })))
-            .filter(|&b| b)
-            .count()
+        for (target, elves) in proposed {
+            if elves.len() == 1 {
+                next.set_at(target, '#');
+            } else {
+                next.extend(elves.into_iter().map(|pos| (pos, '#')));
+            }
+        }
     }
 }
 
 impl DaySolution {
-    fn parse(&self, input: &str) -> Map {
-        Map::from_iter(input.lines().enumerate().flat_map(|(y, line)| {
-            line.chars().enumerate().filter_map(move |(x, c)| {
-                (c == '#').then_some(Position {
-                    x: x as isize,
-                    y: y as isize,
-                })
-            })
-        }))
+    fn parse(&self, input: &str) -> Result<Map> {
+        Ok(Map::from_matrix(
+            Matrix::parse_with(input, |ch, _| Ok(ch))?,
+            |&ch| ch == '#',
+        ))
     }
 }
 
 impl Solution for DaySolution {
     fn part1(&self, input: &str) -> Result<Box<dyn Display>> {
-        let mut map = self.parse(input);
-        let mut directions = [
-            Direction::North,
-            Direction::South,
-            Direction::West,
-            Direction::East,
-        ];
-
-        for _ in 0..10 {
-            let mut keep = HashSet::new();
-            let mut proposed = HashMap::new();
-
-            for pos in map.iter() {
-                let do_not_move = DIRECTIONS_8
-                    .iter()
-                    .map(|offset| *pos + *offset)
-                    .all(|p| !map.contains(&p));
-
-                if do_not_move {
-                    keep.insert(*pos);
-                    continue;
-                }
-
-                let mut is_moved = false;
-                for dir in directions.iter() {
-                    if dir
-                        .to_offsets()
-                        .iter()
-                        .map(|offset| *pos + *offset)
-                        .all(|p| !map.contains(&p))
-                    {
-                        let new_pos = *pos + dir.to_offset();
-                        proposed.entry(new_pos).or_insert_with(Vec::new).push(*pos);
-
-                        is_moved = true;
-                        break;
-                    }
-                }
-
-                if !is_moved {
-                    keep.insert(*pos);
-                }
-            }
-
-            map = keep.clone();
-            for (k, v) in proposed.iter() {
-                if v.len() == 1 {
-                    map.insert(*k);
-                } else {
-                    map.extend(v.iter());
-                }
-            }
-
-            directions.rotate_left(1);
-        }
+        let mut elves = Automaton::new(self.parse(input)?, Elves);
+        let map = elves.run(10);
 
-        Ok(Box::new(map.count_empty()))
+        Ok(Box::new(map.width() * map.height() - map.len()))
     }
 
     fn part2(&self, input: &str) -> Result<Box<dyn Display>> {
-        let mut map = self.parse(input);
-        let mut directions = [
-            Direction::North,
-            Direction::South,
-            Direction::West,
-            Direction::East,
-        ];
-
-        let mut round = 0;
-        loop {
-            let mut keep = HashSet::new();
-            let mut proposed = HashMap::new();
-
-            for pos in map.iter() {
-                let do_not_move = DIRECTIONS_8
-                    .iter()
-                    .map(|offset| *pos + *offset)
-                    .all(|p| !map.contains(&p));
-
-                if do_not_move {
-                    keep.insert(*pos);
-                    continue;
-                }
-
-                let mut is_moved = false;
-                for dir in directions.iter() {
-                    if dir
-                        .to_offsets()
-                        .iter()
-                        .map(|offset| *pos + *offset)
-                        .all(|p| !map.contains(&p))
-                    {
-                        let new_pos = *pos + dir.to_offset();
-                        proposed.entry(new_pos).or_insert_with(Vec::new).push(*pos);
-
-                        is_moved = true;
-                        break;
-                    }
-                }
-
-                if !is_moved {
-                    keep.insert(*pos);
-                }
-            }
-
-            let prev_map = map.clone();
-            map = keep.clone();
-            for (k, v) in proposed.iter() {
-                if v.len() == 1 {
-                    map.insert(*k);
-                } else {
-                    map.extend(v.iter());
-                }
-            }
-
-            if prev_map == map {
-                break;
-            }
-
-            round += 1;
-            directions.rotate_left(1);
-        }
+        let mut elves = Automaton::new(self.parse(input)?, Elves);
 
-        Ok(Box::new(round + 1))
+        Ok(Box::new(elves.run_until_stable()))
     }
 }
 
@@ -1,14 +1,14 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fmt::Display,
-};
+use std::{collections::HashSet, fmt::Display};
 
 use anyhow::Result;
 use aoc::{Runnable, Solution};
 use aoc_derive::Runner;
-use itertools::Itertools;
 use pathfinding::prelude::astar;
 
+use crate::cycle::{Cycle, CycleDetector};
+use crate::geometry::Point2;
+use crate::matrix::Matrix;
+
 #[derive(Runner)]
 #[aoc(file = "inputs/day24.txt")]
 pub struct DaySolution {
@@ -32,88 +32,80 @@ const DIRECTIONS: [Direction; 4] = [
 ];
 
 impl Direction {
-    fn from_char(c: char) -> Self {
+    fn from_char(c: char) -> Option<Self> {
         match c {
-            '^' => Direction::Up,
-            'v' => Direction::Down,
-            '<' => Direction::Left,
-            '>' => Direction::Right,
-            _ => unreachable!(),
+            '^' => Some(Direction::Up),
+            'v' => Some(Direction::Down),
+            '<' => Some(Direction::Left),
+            '>' => Some(Direction::Right),
+            _ => None,
         }
     }
 
-    fn offset(&self) -> Position {
+    fn offset(&self) -> Point2 {
         match self {
-            Direction::Up => Position { x: 0, y: -1 },
-            Direction::Down => Position { x: 0, y: 1 },
-            Direction::Left => Position { x: -1, y: 0 },
-            Direction::Right => Position { x: 1, y: 0 },
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Position {
-    x: i32,
-    y: i32,
-}
-
-impl std::ops::Add for Position {
-    type Output = Self;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        Position {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
+            Direction::Up => Point2::xy(0, -1),
+            Direction::Down => Point2::xy(0, 1),
+            Direction::Left => Point2::xy(-1, 0),
+            Direction::Right => Point2::xy(1, 0),
         }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Blizzard {
-    position: Position,
+    position: Point2,
     direction: Direction,
 }
 
 impl Blizzard {
-    fn tick(&mut self, max_x: i32, max_y: i32) {
-        self.position = self.position + self.direction.offset();
+    fn tick(&mut self, max_x: i64, max_y: i64) {
+        self.position += self.direction.offset();
 
-        if self.position.x <= 0 && self.direction == Direction::Left {
-            self.position.x = max_x;
-        } else if self.position.x > max_x && self.direction == Direction::Right {
-            self.position.x = 1;
+        if self.position.x() <= 0 && self.direction == Direction::Left {
+            self.position[0] = max_x;
+        } else if self.position.x() > max_x && self.direction == Direction::Right {
+            self.position[0] = 1;
         }
 
-        if self.position.y <= 0 && self.direction == Direction::Up {
-            self.position.y = max_y;
-        } else if self.position.y > max_y && self.direction == Direction::Down {
-            self.position.y = 1;
+        if self.position.y() <= 0 && self.direction == Direction::Up {
+            self.position[1] = max_y;
+        } else if self.position.y() > max_y && self.direction == Direction::Down {
+            self.position[1] = 1;
         }
     }
 }
 
+/// Blizzard positions for every minute up to the point where they start repeating.
+struct BlizzardCache {
+    states: Vec<HashSet<Point2>>,
+    cycle: Cycle,
+}
+
+impl BlizzardCache {
+    fn at(&self, time: i64) -> &HashSet<Point2> {
+        &self.states[self.cycle.reduce(time as usize)]
+    }
+}
+
 impl DaySolution {
-    fn parse(&self, input: &str) -> Result<(Vec<Blizzard>, (i32, i32))> {
-        let max_y = input.lines().count() as i32 - 2;
-        let max_x = input.lines().next().unwrap().chars().count() as i32 - 2;
-
-        let blizzards = input
-            .lines()
-            .enumerate()
-            .flat_map(|(y, line)| {
-                line.chars().enumerate().flat_map(move |(x, c)| {
-                    if c == '.' || c == '#' {
-                        None
-                    } else {
-                        Some(Blizzard {
-                            position: Position {
-                                x: x as i32,
-                                y: y as i32,
-                            },
-                            direction: Direction::from_char(c),
-                        })
-                    }
+    fn parse(&self, input: &str) -> Result<(Vec<Blizzard>, (i64, i64))> {
+        let matrix = Matrix::parse_with(input, |ch, (x, y)| match ch {
+            '.' | '#' => Ok(None),
+            ch => Direction::from_char(ch).map(Some).ok_or_else(|| {
+                anyhow::anyhow!("Invalid tile {ch:?} at line {}, column {}", y + 1, x + 1)
+            }),
+        })?;
+
+        let max_x = matrix.width as i64 - 2;
+        let max_y = matrix.height as i64 - 2;
+
+        let blizzards = matrix
+            .iter()
+            .filter_map(|(direction, (x, y))| {
+                direction.map(|direction| Blizzard {
+                    position: Point2::xy(x as i64, y as i64),
+                    direction,
                 })
             })
             .collect::<Vec<_>>();
@@ -121,45 +113,37 @@ impl DaySolution {
         Ok((blizzards, (max_x, max_y)))
     }
 
-    fn warmup_cache(
-        &self,
-        blizzards: &[Blizzard],
-        max_x: i32,
-        max_y: i32,
-    ) -> HashMap<i32, HashSet<Position>> {
-        let mut cache = HashMap::new();
+    fn warmup_cache(&self, blizzards: &[Blizzard], max_x: i64, max_y: i64) -> BlizzardCache {
+        let mut states = vec![];
+        let mut detector = CycleDetector::new();
         let mut blizzards = blizzards.to_owned();
 
-        for i in 0.. {
-            let hashset = blizzards.iter().map(|b| b.position).collect::<HashSet<_>>();
+        loop {
+            let positions = blizzards.iter().map(|b| b.position).collect::<Vec<_>>();
 
-            if cache.values().contains(&hashset) {
-                break;
+            if let Some(cycle) = detector.observe(positions.clone()) {
+                return BlizzardCache { states, cycle };
             }
 
-            cache.insert(i, hashset);
+            states.push(positions.into_iter().collect());
             blizzards.iter_mut().for_each(|b| b.tick(max_x, max_y));
         }
-
-        cache
     }
 
     fn find_path(
         &self,
-        start: Position,
-        end: Position,
-        max_x: i32,
-        max_y: i32,
-        time: i32,
-        cache: &HashMap<i32, HashSet<Position>>,
-    ) -> i32 {
-        let cache_size = cache.len() as i32;
-
+        start: Point2,
+        end: Point2,
+        max_x: i64,
+        max_y: i64,
+        time: i64,
+        cache: &BlizzardCache,
+    ) -> i64 {
         let result = astar(
             &(start, time),
             |&(p, time)| {
                 let mut next = vec![];
-                let blizzard = cache.get(&((time + 1) % cache_size)).unwrap();
+                let blizzard = cache.at(time + 1);
 
                 for direction in DIRECTIONS {
                     let possible = p + direction.offset();
@@ -167,10 +151,10 @@ impl DaySolution {
                     if possible == end {
                         next.push(((possible, time + 1), 1));
                         continue;
-                    } else if possible.x <= 0
-                        || possible.x > max_x
-                        || possible.y <= 0
-                        || possible.y > max_y
+                    } else if possible.x() <= 0
+                        || possible.x() > max_x
+                        || possible.y() <= 0
+                        || possible.y() > max_y
                     {
                         continue;
                     }
@@ -188,7 +172,7 @@ impl DaySolution {
             },
             |(p, _)| {
                 // manhattan distance
-                (p.x - end.x).abs() + (p.y - end.y).abs() / 3
+                (p.x() - end.x()).abs() + (p.y() - end.y()).abs() / 3
             },
             |&(p, _)| p == end,
         );
@@ -197,26 +181,21 @@ impl DaySolution {
         cost
     }
 
-    fn solve(&self, input: &str, count: usize) -> Result<i32> {
+    fn solve(&self, input: &str, count: usize) -> Result<i64> {
         let (blizzards, (max_x, max_y)) = self.parse(input)?;
         let cache = self.warmup_cache(&blizzards, max_x, max_y);
 
-        let start = Position { x: 1, y: 0 };
-        let end = Position {
-            x: max_x,
-            y: max_y + 1,
-        };
+        let start = Point2::xy(1, 0);
+        let end = Point2::xy(max_x, max_y + 1);
 
         let (sum, _, _) =
-            std::iter::repeat(())
-                .take(count)
-                .fold((0, start, end), |(time, start, end), _| {
-                    (
-                        time + self.find_path(start, end, max_x, max_y, time, &cache),
-                        end,
-                        start,
-                    )
-                });
+            std::iter::repeat_n((), count).fold((0, start, end), |(time, start, end), _| {
+                (
+                    time + self.find_path(start, end, max_x, max_y, time, &cache),
+                    end,
+                    start,
+                )
+            });
 
         Ok(sum)
     }
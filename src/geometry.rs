@@ -0,0 +1,421 @@
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Index, IndexMut, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point<const N: usize>(pub [i64; N]);
+
+#[allow(dead_code)]
+pub type Point2 = Point<2>;
+
+#[allow(dead_code)]
+pub type Point3 = Point<3>;
+
+impl<const N: usize> Default for Point<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> Point<N> {
+    #[allow(dead_code)]
+    pub const fn new(coords: [i64; N]) -> Self {
+        Self(coords)
+    }
+
+    #[allow(dead_code)]
+    pub fn manhattan(&self, other: &Self) -> u64 {
+        (0..N).map(|i| self.0[i].abs_diff(other.0[i])).sum()
+    }
+
+    #[allow(dead_code)]
+    pub fn chebyshev(&self, other: &Self) -> u64 {
+        (0..N)
+            .map(|i| self.0[i].abs_diff(other.0[i]))
+            .max()
+            .unwrap_or_default()
+    }
+
+    #[allow(dead_code)]
+    pub fn dot(&self, other: &Self) -> i64 {
+        (0..N).map(|i| self.0[i] * other.0[i]).sum()
+    }
+
+    #[allow(dead_code)]
+    pub fn signum(&self) -> Self {
+        Self(self.0.map(i64::signum))
+    }
+
+    #[allow(dead_code)]
+    pub fn componentwise_min(&self, other: &Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i].min(other.0[i])))
+    }
+
+    #[allow(dead_code)]
+    pub fn componentwise_max(&self, other: &Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i].max(other.0[i])))
+    }
+
+    #[allow(dead_code)]
+    pub fn neighbours<'a>(&'a self, offsets: &'a [Self]) -> impl Iterator<Item = Self> + 'a {
+        offsets.iter().map(move |&offset| *self + offset)
+    }
+}
+
+impl Point<2> {
+    #[allow(dead_code)]
+    pub const fn xy(x: i64, y: i64) -> Self {
+        Self([x, y])
+    }
+
+    #[allow(dead_code)]
+    pub fn x(&self) -> i64 {
+        self.0[0]
+    }
+
+    #[allow(dead_code)]
+    pub fn y(&self) -> i64 {
+        self.0[1]
+    }
+
+    pub fn try_new<P>(x: P, y: P) -> Option<Self>
+    where
+        P: TryInto<i64>,
+    {
+        Some(Self([x.try_into().ok()?, y.try_into().ok()?]))
+    }
+
+    pub fn checked_offset(&self, (dx, dy): (i32, i32)) -> Option<Self> {
+        Some(Self([
+            self.x().checked_add(i64::from(dx))?,
+            self.y().checked_add(i64::from(dy))?,
+        ]))
+    }
+
+    pub fn to_usize(self) -> Option<(usize, usize)> {
+        Some((self.x().try_into().ok()?, self.y().try_into().ok()?))
+    }
+}
+
+impl Point<3> {
+    #[allow(dead_code)]
+    pub const fn xyz(x: i64, y: i64, z: i64) -> Self {
+        Self([x, y, z])
+    }
+}
+
+/// Offsets to the `2 * N` neighbours sharing a face.
+#[allow(dead_code)]
+pub fn orthogonal_offsets<const N: usize>() -> Vec<Point<N>> {
+    (0..N)
+        .flat_map(|axis| {
+            [-1, 1].map(|delta| {
+                let mut offset = Point::default();
+                offset.0[axis] = delta;
+                offset
+            })
+        })
+        .collect()
+}
+
+/// Offsets to all `3^N - 1` neighbours, diagonals included.
+#[allow(dead_code)]
+pub fn all_offsets<const N: usize>() -> Vec<Point<N>> {
+    (0..3usize.pow(N as u32))
+        .map(|mut index| {
+            Point(std::array::from_fn(|_| {
+                let delta = (index % 3) as i64 - 1;
+                index /= 3;
+                delta
+            }))
+        })
+        .filter(|offset| *offset != Point::default())
+        .collect()
+}
+
+#[allow(dead_code)]
+pub fn bounds<const N: usize, I>(points: I) -> Option<(Point<N>, Point<N>)>
+where
+    I: IntoIterator<Item = Point<N>>,
+{
+    points.into_iter().fold(None, |bounds, point| {
+        let (min, max) = bounds.unwrap_or((point, point));
+        Some((min.componentwise_min(&point), max.componentwise_max(&point)))
+    })
+}
+
+impl<const N: usize> Add for Point<N> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(std::array::from_fn(|i| self.0[i] + rhs.0[i]))
+    }
+}
+
+impl<const N: usize> AddAssign for Point<N> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const N: usize> Sub for Point<N> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(std::array::from_fn(|i| self.0[i] - rhs.0[i]))
+    }
+}
+
+impl<const N: usize> SubAssign for Point<N> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<const N: usize> Neg for Point<N> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self(self.0.map(|v| -v))
+    }
+}
+
+impl<const N: usize> Mul<i64> for Point<N> {
+    type Output = Self;
+
+    fn mul(self, rhs: i64) -> Self::Output {
+        Self(self.0.map(|v| v * rhs))
+    }
+}
+
+impl<const N: usize> Index<usize> for Point<N> {
+    type Output = i64;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+impl<const N: usize> IndexMut<usize> for Point<N> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.0[index]
+    }
+}
+
+impl<const N: usize> FromStr for Point<N> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coords = s
+            .split(',')
+            .map(|v| v.trim().parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self(coords.try_into().map_err(|coords: Vec<i64>| {
+            anyhow::anyhow!("Expected {N} coordinates, got {}", coords.len())
+        })?))
+    }
+}
+
+impl<const N: usize> Display for Point<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(")?;
+        for (i, v) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{v}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl From<(i64, i64)> for Point<2> {
+    fn from((x, y): (i64, i64)) -> Self {
+        Self([x, y])
+    }
+}
+
+impl TryFrom<(usize, usize)> for Point<2> {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from((x, y): (usize, usize)) -> Result<Self, Self::Error> {
+        Ok(Self([x.try_into()?, y.try_into()?]))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T, const N: usize> {
+    origin: Point<N>,
+    dims: [usize; N],
+    data: Vec<T>,
+}
+
+impl<T, const N: usize> Grid<T, N> {
+    #[allow(dead_code)]
+    pub fn new(origin: Point<N>, dims: [usize; N]) -> Self
+    where
+        T: Default,
+    {
+        let mut data = Vec::new();
+        data.resize_with(dims.iter().product(), T::default);
+
+        Self { origin, dims, data }
+    }
+
+    /// Grid covering `min..=max` on every axis.
+    #[allow(dead_code)]
+    pub fn from_bounds(min: Point<N>, max: Point<N>) -> Self
+    where
+        T: Default,
+    {
+        let dims = std::array::from_fn(|i| (max.0[i] - min.0[i] + 1).max(0) as usize);
+        Self::new(min, dims)
+    }
+
+    #[allow(dead_code)]
+    pub fn origin(&self) -> Point<N> {
+        self.origin
+    }
+
+    #[allow(dead_code)]
+    pub fn dims(&self) -> [usize; N] {
+        self.dims
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn index_of(&self, point: &Point<N>) -> Option<usize> {
+        let mut index = 0usize;
+        for axis in (0..N).rev() {
+            let offset = point.0[axis].checked_sub(self.origin.0[axis])?;
+            let offset = usize::try_from(offset).ok()?;
+            if offset >= self.dims[axis] {
+                return None;
+            }
+            index = index * self.dims[axis] + offset;
+        }
+
+        Some(index)
+    }
+
+    #[allow(dead_code)]
+    pub fn point_of(&self, mut index: usize) -> Option<Point<N>> {
+        if index >= self.data.len() {
+            return None;
+        }
+
+        let mut point = self.origin;
+        for axis in 0..N {
+            point.0[axis] += (index % self.dims[axis]) as i64;
+            index /= self.dims[axis];
+        }
+
+        Some(point)
+    }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, point: &Point<N>) -> bool {
+        self.index_of(point).is_some()
+    }
+
+    #[allow(dead_code)]
+    pub fn get(&self, point: &Point<N>) -> Option<&T> {
+        self.index_of(point).map(|index| &self.data[index])
+    }
+
+    #[allow(dead_code)]
+    pub fn get_mut(&mut self, point: &Point<N>) -> Option<&mut T> {
+        self.index_of(point).map(|index| &mut self.data[index])
+    }
+
+    #[allow(dead_code)]
+    pub fn set(&mut self, point: &Point<N>, value: T) -> bool {
+        match self.get_mut(point) {
+            Some(cell) => {
+                *cell = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = (&T, Point<N>)> {
+        self.data
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| self.point_of(index).map(|point| (value, point)))
+    }
+
+    #[allow(dead_code)]
+    pub fn neighbours<'a>(
+        &'a self,
+        point: &'a Point<N>,
+        offsets: &'a [Point<N>],
+    ) -> impl Iterator<Item = (&'a T, Point<N>)> + 'a {
+        point
+            .neighbours(offsets)
+            .filter_map(|neighbour| self.get(&neighbour).map(|value| (value, neighbour)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_point_arithmetic() {
+        let a = Point::xyz(1, -2, 3);
+        let b = "4, 2, -1".parse::<Point3>().unwrap();
+
+        assert_eq!(a + b, Point::xyz(5, 0, 2));
+        assert_eq!(b - a, Point::xyz(3, 4, -4));
+        assert_eq!(-a * 2, Point::xyz(-2, 4, -6));
+        assert_eq!((b - a).signum(), Point::xyz(1, 1, -1));
+        assert_eq!(a.manhattan(&b), 11);
+        assert_eq!(a.chebyshev(&b), 4);
+        assert_eq!(a.dot(&b), -3);
+        assert!("1,2".parse::<Point3>().is_err());
+        assert_eq!(Point::xy(1, 2).to_string(), "(1,2)");
+    }
+
+    #[test]
+    fn test_offsets() {
+        assert_eq!(orthogonal_offsets::<2>().len(), 4);
+        assert_eq!(orthogonal_offsets::<3>().len(), 6);
+        assert_eq!(all_offsets::<2>().len(), 8);
+        assert_eq!(all_offsets::<3>().len(), 26);
+        assert!(orthogonal_offsets::<4>()
+            .iter()
+            .all(|offset| offset.manhattan(&Point::default()) == 1));
+    }
+
+    #[test]
+    fn test_grid() {
+        let mut grid = Grid::<u8, 3>::from_bounds(Point::xyz(-1, -1, -1), Point::xyz(1, 2, 3));
+        assert_eq!(grid.dims(), [3, 4, 5]);
+        assert_eq!(grid.len(), 60);
+
+        assert!(grid.set(&Point::xyz(1, 2, 3), 7));
+        assert!(!grid.set(&Point::xyz(2, 0, 0), 7));
+        assert_eq!(grid.get(&Point::xyz(1, 2, 3)), Some(&7));
+        assert_eq!(grid.get(&Point::xyz(-2, 0, 0)), None);
+
+        let (_, point) = grid.iter().find(|(&v, _)| v == 7).unwrap();
+        assert_eq!(point, Point::xyz(1, 2, 3));
+        assert_eq!(
+            grid.neighbours(&Point::xyz(1, 2, 3), &orthogonal_offsets())
+                .count(),
+            3
+        );
+    }
+}
@@ -1,5 +1,8 @@
 use aoc::{count, runners, Runnable};
 
+mod automaton;
+mod cycle;
+mod geometry;
 mod matrix;
 
 mod day01;
@@ -36,11 +39,57 @@ macro_rules! regex {
     }};
 }
 
+/// Extra renderings some days offer besides their answers, e.g. `y2022 21 dot`. Arguments
+/// after the view name are passed on as options.
+fn view(day: usize, name: &str, options: &[String]) -> anyhow::Result<String> {
+    fn input(runner: &dyn Runnable) -> anyhow::Result<String> {
+        let filename = runner.filename();
+        std::fs::read_to_string(&filename)
+            .map_err(|_| anyhow::anyhow!("Input file {filename} not found"))
+    }
+
+    match day {
+        16 => {
+            let day = day16::DaySolution::default();
+            day.view(&input(&day)?, name)
+        }
+        17 => {
+            let day = day17::DaySolution::default();
+            day.view(&input(&day)?, name, options)
+        }
+        19 => {
+            let day = day19::DaySolution::default();
+            day.view(&input(&day)?, name)
+        }
+        21 => {
+            let day = day21::DaySolution::default();
+            day.view(&input(&day)?, name)
+        }
+        22 => {
+            let day = day22::DaySolution::default();
+            day.view(&input(&day)?, name)
+        }
+        _ => anyhow::bail!("Day {day} has no views"),
+    }
+}
+
 fn main() {
     let day = std::env::args()
         .nth(1)
         .and_then(|v| v.parse::<usize>().ok());
 
+    if let (Some(day), Some(name)) = (day, std::env::args().nth(2)) {
+        let options = std::env::args().skip(3).collect::<Vec<_>>();
+        match view(day, &name, &options) {
+            Ok(view) => print!("{view}"),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     runners!(
         day01, day02, day03, day04, day05, day06, day07, day08, day09, day10, day11, day12, day13,
         day14, day15, day16, day17, day18, day19, day20, day21, day22, day23, day24, day25
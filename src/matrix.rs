@@ -1,7 +1,20 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::str::FromStr;
 
-#[derive(Clone)]
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::geometry::Point2;
+
+/// Grid position; the same type as the 2D geometry point.
+pub type Coord = Point2;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Matrix<T> {
     pub width: usize,
     pub height: usize,
@@ -60,16 +73,13 @@ impl<T> Matrix<T> {
     }
 }
 
-impl<T: Debug + Default> Debug for Matrix<T> {
+impl<T: Debug> Debug for Matrix<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Matrix({}x{})", self.width, self.height)?;
 
-        for y in 0..self.height {
-            for x in 0..self.width {
-                match self.get(x, y) {
-                    Some(x) => write!(f, "{:?}", x)?,
-                    None => write!(f, " ")?,
-                };
+        for row in self.rows() {
+            for value in row {
+                write!(f, "{:?}", value)?;
             }
             writeln!(f)?;
         }
@@ -78,14 +88,11 @@ impl<T: Debug + Default> Debug for Matrix<T> {
     }
 }
 
-impl<T: Display + Default> Display for Matrix<T> {
+impl<T: Display> Display for Matrix<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                match self.get(x, y) {
-                    Some(x) => write!(f, "{}", x)?,
-                    None => write!(f, " ")?,
-                };
+        for row in self.rows() {
+            for value in row {
+                write!(f, "{}", value)?;
             }
             writeln!(f)?;
         }
@@ -128,70 +135,93 @@ impl<T> Matrix<T> {
         }
     }
 
-    fn get_index<P>(&self, x: P, y: P) -> Option<usize>
-    where
-        P: TryInto<i32>,
-    {
-        let x = x.try_into().ok().unwrap();
-        let y = y.try_into().ok().unwrap();
-        if x < 0 || x as usize >= self.width || y < 0 || y as usize >= self.height {
+    fn get_index(&self, coord: Coord) -> Option<usize> {
+        let (x, y) = coord.to_usize()?;
+        if x >= self.width || y >= self.height {
             return None;
         }
-        let position = x + y * self.width as i32;
-        Some(position as usize)
+
+        y.checked_mul(self.width)?.checked_add(x)
+    }
+
+    #[allow(dead_code)]
+    pub fn coord_of(&self, index: usize) -> Option<Coord> {
+        if index >= self.data.len() {
+            return None;
+        }
+
+        Coord::try_new(index % self.width, index / self.width)
+    }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, coord: Coord) -> bool {
+        self.get_index(coord).is_some()
     }
 
     pub fn get<P>(&self, x: P, y: P) -> Option<&T>
     where
-        P: TryInto<i32>,
+        P: TryInto<i64>,
     {
-        self.get_index(x, y).and_then(|index| self.data.get(index))
+        Coord::try_new(x, y).and_then(|coord| self.get_at(coord))
+    }
+
+    pub fn get_at(&self, coord: Coord) -> Option<&T> {
+        self.get_index(coord).and_then(|index| self.data.get(index))
     }
 
     #[allow(dead_code)]
     pub fn get_mut<P>(&mut self, x: P, y: P) -> Option<&mut T>
     where
-        P: TryInto<i32>,
+        P: TryInto<i64>,
     {
-        self.get_index(x, y)
+        Coord::try_new(x, y).and_then(|coord| self.get_at_mut(coord))
+    }
+
+    #[allow(dead_code)]
+    pub fn get_at_mut(&mut self, coord: Coord) -> Option<&mut T> {
+        self.get_index(coord)
             .and_then(|index| self.data.get_mut(index))
     }
 
     #[allow(dead_code)]
-    pub fn set<P>(&mut self, x: P, y: P, value: T)
+    pub fn set<P>(&mut self, x: P, y: P, value: T) -> bool
     where
-        P: TryInto<i32>,
+        P: TryInto<i64>,
     {
-        if let Some(index) = self.get_index(x, y) {
-            self.data[index] = value;
+        match Coord::try_new(x, y) {
+            Some(coord) => self.set_at(coord, value),
+            None => false,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn set_at(&mut self, coord: Coord, value: T) -> bool {
+        match self.get_at_mut(coord) {
+            Some(cell) => {
+                *cell = value;
+                true
+            }
+            None => false,
         }
     }
 
     #[allow(dead_code)]
     pub fn neighbours4<P>(&self, x: P, y: P) -> Vec<&T>
     where
-        P: TryInto<i32>,
+        P: TryInto<i64>,
     {
-        let x = x.try_into().ok().unwrap();
-        let y = y.try_into().ok().unwrap();
-
-        MATRIX_NEIGHBOURS_4
-            .iter()
-            .filter_map(|(dx, dy)| self.get(x + dx, y + dy))
+        self.neighbours_iter(&MATRIX_NEIGHBOURS_4, x, y)
+            .map(|(value, _)| value)
             .collect()
     }
 
     #[allow(dead_code)]
     pub fn neighbours8<P>(&self, x: P, y: P) -> Vec<&T>
     where
-        P: TryInto<i32>,
+        P: TryInto<i64>,
     {
-        let x = x.try_into().ok().unwrap();
-        let y = y.try_into().ok().unwrap();
-
-        MATRIX_NEIGHBOURS_8
-            .iter()
-            .filter_map(|(dx, dy)| self.get(x + dx, y + dy))
+        self.neighbours_iter(&MATRIX_NEIGHBOURS_8, x, y)
+            .map(|(value, _)| value)
             .collect()
     }
 
@@ -201,31 +231,30 @@ impl<T> Matrix<T> {
         offsets: &'a [(i32, i32)],
         x: P,
         y: P,
-    ) -> impl Iterator<Item = (&'a T, (i32, i32))> + 'a
+    ) -> impl Iterator<Item = (&'a T, Coord)> + 'a
     where
-        P: TryInto<i32>,
+        P: TryInto<i64>,
     {
-        let x = x.try_into().ok().unwrap();
-        let y = y.try_into().ok().unwrap();
+        let coord = Coord::try_new(x, y);
 
-        offsets.iter().filter_map(move |(dx, dy)| {
-            self.get(x + dx, y + dy)
-                .map(|value| (value, (x + dx, y + dy)))
+        offsets.iter().filter_map(move |&offset| {
+            let neighbour = coord?.checked_offset(offset)?;
+            self.get_at(neighbour).map(|value| (value, neighbour))
         })
     }
 
     #[allow(dead_code)]
-    pub fn neighbours8_iter<P>(&self, x: P, y: P) -> impl Iterator<Item = (&T, (i32, i32))>
+    pub fn neighbours8_iter<P>(&self, x: P, y: P) -> impl Iterator<Item = (&T, Coord)>
     where
-        P: TryInto<i32>,
+        P: TryInto<i64>,
     {
         self.neighbours_iter(&MATRIX_NEIGHBOURS_8, x, y)
     }
 
     #[allow(dead_code)]
-    pub fn neighbours4_iter<P>(&self, x: P, y: P) -> impl Iterator<Item = (&T, (i32, i32))>
+    pub fn neighbours4_iter<P>(&self, x: P, y: P) -> impl Iterator<Item = (&T, Coord)>
     where
-        P: TryInto<i32>,
+        P: TryInto<i64>,
     {
         self.neighbours_iter(&MATRIX_NEIGHBOURS_4, x, y)
     }
@@ -246,50 +275,1884 @@ impl<T> Matrix<T> {
     }
 }
 
-impl<T: Default + FromStr> Matrix<T> {
+impl<T> Matrix<T> {
     #[allow(dead_code)]
-    pub fn from(s: &str) -> Option<Matrix<T>> {
-        let lines = s.lines().map(|l| l.to_string()).collect::<Vec<String>>();
-        let width = match lines.get(0) {
-            None => return None,
-            Some(s) => s.len(),
-        };
-        let height = lines.len();
+    pub fn row(&self, y: usize) -> Option<&[T]> {
+        if y >= self.height {
+            return None;
+        }
 
-        let data = lines
-            .iter()
-            .flat_map(|line| {
-                line.chars()
-                    .map(|ch| ch.to_string().parse::<T>().unwrap_or_default())
-                    .collect::<Vec<T>>()
-            })
-            .collect::<Vec<T>>();
+        Some(&self.data[y * self.width..(y + 1) * self.width])
+    }
+
+    #[allow(dead_code)]
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.data.chunks(self.width.max(1))
+    }
+
+    #[allow(dead_code)]
+    pub fn column(&self, x: usize) -> Option<impl Iterator<Item = &T>> {
+        if x >= self.width {
+            return None;
+        }
+
+        Some(self.data.iter().skip(x).step_by(self.width))
+    }
+
+    #[allow(dead_code)]
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).flat_map(|x| self.column(x))
+    }
+
+    #[allow(dead_code)]
+    pub fn view(
+        &self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Option<MatrixView<'_, T>> {
+        if x.checked_add(width)? > self.width || y.checked_add(height)? > self.height {
+            return None;
+        }
 
-        Some(Self {
+        Some(MatrixView {
+            matrix: self,
+            x,
+            y,
             width,
             height,
-            data,
         })
     }
 
     #[allow(dead_code)]
-    pub fn from_separated(s: &str, pat: &str) -> Option<Matrix<T>> {
-        let lines = s.lines().map(|l| l.to_string()).collect::<Vec<String>>();
-        let width = match lines.get(0) {
-            None => return None,
-            Some(s) => s.len(),
+    pub fn ray<P>(&self, x: P, y: P, direction: (i32, i32)) -> Ray<'_, T>
+    where
+        P: TryInto<i64>,
+    {
+        Ray {
+            matrix: self,
+            current: Coord::try_new(x, y).filter(|_| direction != (0, 0)),
+            direction,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn rays<'a, P>(
+        &'a self,
+        offsets: &'a [(i32, i32)],
+        x: P,
+        y: P,
+    ) -> impl Iterator<Item = Ray<'a, T>> + 'a
+    where
+        P: TryInto<i64> + Copy + 'a,
+    {
+        offsets
+            .iter()
+            .map(move |&direction| self.ray(x, y, direction))
+    }
+}
+
+pub struct MatrixView<'a, T> {
+    matrix: &'a Matrix<T>,
+    x: usize,
+    y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl<'a, T> MatrixView<'a, T> {
+    #[allow(dead_code)]
+    pub fn to_matrix(&self) -> Matrix<T>
+    where
+        T: Clone,
+    {
+        Matrix {
+            width: self.width,
+            height: self.height,
+            data: self.rows().flatten().cloned().collect(),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn get(&self, x: usize, y: usize) -> Option<&'a T> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        self.matrix.get(self.x + x, self.y + y)
+    }
+
+    #[allow(dead_code)]
+    pub fn rows(&self) -> impl Iterator<Item = &'a [T]> + '_ {
+        (self.y..self.y + self.height)
+            .flat_map(|y| self.matrix.row(y))
+            .map(|row| &row[self.x..self.x + self.width])
+    }
+
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = (&'a T, (usize, usize))> + '_ {
+        self.rows().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(x, value)| (value, (x, y)))
+        })
+    }
+}
+
+pub struct Ray<'a, T> {
+    matrix: &'a Matrix<T>,
+    current: Option<Coord>,
+    direction: (i32, i32),
+}
+
+impl<'a, T> Ray<'a, T> {
+    #[allow(dead_code)]
+    pub fn take_until<F>(self, mut predicate: F) -> impl Iterator<Item = (&'a T, Coord)>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut blocked = false;
+        self.take_while(move |(value, _)| {
+            if blocked {
+                return false;
+            }
+
+            blocked = predicate(value);
+            true
+        })
+    }
+}
+
+impl<'a, T> Iterator for Ray<'a, T> {
+    type Item = (&'a T, Coord);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.current?.checked_offset(self.direction);
+        let value = next.and_then(|coord| self.matrix.get_at(coord));
+
+        self.current = value.and(next);
+        value.zip(next)
+    }
+}
+
+impl<T> Matrix<T> {
+    fn remap<F>(&self, width: usize, height: usize, source: F) -> Matrix<T>
+    where
+        T: Clone,
+        F: Fn(usize, usize) -> (usize, usize),
+    {
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = source(x, y);
+                data.push(self.data[sy * self.width + sx].clone());
+            }
+        }
+
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    fn remap_in_place<F>(&mut self, width: usize, height: usize, source: F)
+    where
+        F: Fn(usize, usize) -> (usize, usize),
+    {
+        let old_width = self.width;
+        let source_index = |index: usize| {
+            let (sx, sy) = source(index % width, index / width);
+            sy * old_width + sx
         };
-        let height = lines.len();
-        let mut data: Vec<T> = vec![];
 
-        for line in lines {
-            data.extend(line.split(pat).map(|s| s.parse::<T>().unwrap_or_default()));
+        let mut visited = vec![false; self.data.len()];
+        for start in 0..self.data.len() {
+            if visited[start] {
+                continue;
+            }
+
+            let mut current = start;
+            loop {
+                visited[current] = true;
+                let next = source_index(current);
+                if next == start {
+                    break;
+                }
+
+                self.data.swap(current, next);
+                current = next;
+            }
+        }
+
+        self.width = width;
+        self.height = height;
+    }
+
+    #[allow(dead_code)]
+    pub fn transposed(&self) -> Matrix<T>
+    where
+        T: Clone,
+    {
+        self.remap(self.height, self.width, |x, y| (y, x))
+    }
+
+    #[allow(dead_code)]
+    pub fn transpose(&mut self) {
+        self.remap_in_place(self.height, self.width, |x, y| (y, x));
+    }
+
+    #[allow(dead_code)]
+    pub fn rotated_cw(&self) -> Matrix<T>
+    where
+        T: Clone,
+    {
+        let height = self.height;
+        self.remap(self.height, self.width, |x, y| (y, height - 1 - x))
+    }
+
+    #[allow(dead_code)]
+    pub fn rotate_cw(&mut self) {
+        let height = self.height;
+        self.remap_in_place(self.height, self.width, |x, y| (y, height - 1 - x));
+    }
+
+    #[allow(dead_code)]
+    pub fn rotated_ccw(&self) -> Matrix<T>
+    where
+        T: Clone,
+    {
+        let width = self.width;
+        self.remap(self.height, self.width, |x, y| (width - 1 - y, x))
+    }
+
+    #[allow(dead_code)]
+    pub fn rotate_ccw(&mut self) {
+        let width = self.width;
+        self.remap_in_place(self.height, self.width, |x, y| (width - 1 - y, x));
+    }
+
+    #[allow(dead_code)]
+    pub fn rotated_180(&self) -> Matrix<T>
+    where
+        T: Clone,
+    {
+        let mut matrix = self.clone();
+        matrix.rotate_180();
+        matrix
+    }
+
+    #[allow(dead_code)]
+    pub fn rotate_180(&mut self) {
+        self.data.reverse();
+    }
+
+    #[allow(dead_code)]
+    pub fn flipped_horizontal(&self) -> Matrix<T>
+    where
+        T: Clone,
+    {
+        let mut matrix = self.clone();
+        matrix.flip_horizontal();
+        matrix
+    }
+
+    #[allow(dead_code)]
+    pub fn flip_horizontal(&mut self) {
+        self.data
+            .chunks_mut(self.width.max(1))
+            .for_each(|row| row.reverse());
+    }
+
+    #[allow(dead_code)]
+    pub fn flipped_vertical(&self) -> Matrix<T>
+    where
+        T: Clone,
+    {
+        let mut matrix = self.clone();
+        matrix.flip_vertical();
+        matrix
+    }
+
+    #[allow(dead_code)]
+    pub fn flip_vertical(&mut self) {
+        let (width, height) = (self.width, self.height);
+        for y in 0..height / 2 {
+            let (top, bottom) = self.data.split_at_mut((height - 1 - y) * width);
+            top[y * width..(y + 1) * width].swap_with_slice(&mut bottom[..width]);
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Matrix<T>>
+    where
+        T: Clone,
+    {
+        self.view(x, y, width, height).map(|view| view.to_matrix())
+    }
+
+    #[allow(dead_code)]
+    pub fn bounding_box<F>(&self, predicate: F) -> Option<(Coord, Coord)>
+    where
+        F: Fn(&T) -> bool,
+    {
+        self.iter()
+            .filter(|(value, _)| predicate(value))
+            .fold(None, |bounds, (_, (x, y))| {
+                let (min, max) = bounds.unwrap_or(((x, y), (x, y)));
+                Some(((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))))
+            })
+            .and_then(|(min, max)| Some((Coord::try_from(min).ok()?, Coord::try_from(max).ok()?)))
+    }
+
+    #[allow(dead_code)]
+    pub fn crop_to<F>(&self, predicate: F) -> Option<Matrix<T>>
+    where
+        T: Clone,
+        F: Fn(&T) -> bool,
+    {
+        let (min, max) = self.bounding_box(predicate)?;
+        let (min_x, min_y) = min.to_usize()?;
+        let (max_x, max_y) = max.to_usize()?;
+
+        self.crop(min_x, min_y, max_x - min_x + 1, max_y - min_y + 1)
+    }
+
+    #[allow(dead_code)]
+    pub fn pad(&self, margin: usize, fill: T) -> Matrix<T>
+    where
+        T: Clone,
+    {
+        self.pad_sides(margin, margin, margin, margin, fill)
+    }
+
+    #[allow(dead_code)]
+    pub fn pad_sides(
+        &self,
+        top: usize,
+        right: usize,
+        bottom: usize,
+        left: usize,
+        fill: T,
+    ) -> Matrix<T>
+    where
+        T: Clone,
+    {
+        let width = left + self.width + right;
+        let height = top + self.height + bottom;
+
+        let mut data = Vec::with_capacity(width * height);
+        data.resize(top * width, fill.clone());
+        for row in self.rows() {
+            data.resize(data.len() + left, fill.clone());
+            data.extend_from_slice(row);
+            data.resize(data.len() + right, fill.clone());
         }
+        data.resize(width * height, fill);
 
-        Some(Self {
+        Self {
             width,
             height,
             data,
-        })
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RaggedRows {
+    #[default]
+    Reject,
+    Pad,
+}
+
+impl<T: Default + FromStr> Matrix<T> {
+    #[allow(dead_code)]
+    pub fn from(s: &str) -> anyhow::Result<Matrix<T>> {
+        Self::from_with(s, RaggedRows::Reject)
+    }
+
+    #[allow(dead_code)]
+    pub fn from_with(s: &str, ragged: RaggedRows) -> anyhow::Result<Matrix<T>> {
+        Self::from_cells(
+            s.lines().map(|line| {
+                line.char_indices()
+                    .map(move |(index, ch)| &line[index..index + ch.len_utf8()])
+            }),
+            ragged,
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn from_separated(s: &str, pat: &str) -> anyhow::Result<Matrix<T>> {
+        Self::from_separated_with(s, pat, RaggedRows::Reject)
+    }
+
+    #[allow(dead_code)]
+    pub fn from_separated_with(
+        s: &str,
+        pat: &str,
+        ragged: RaggedRows,
+    ) -> anyhow::Result<Matrix<T>> {
+        Self::from_cells(s.lines().map(|line| line.split(pat).map(str::trim)), ragged)
+    }
+
+    fn from_cells<'a, R, C>(rows: R, ragged: RaggedRows) -> anyhow::Result<Matrix<T>>
+    where
+        R: Iterator<Item = C>,
+        C: Iterator<Item = &'a str>,
+    {
+        let rows = rows
+            .enumerate()
+            .map(|(y, cells)| {
+                cells
+                    .enumerate()
+                    .map(|(x, cell)| {
+                        cell.parse::<T>().map_err(|_| {
+                            anyhow::anyhow!(
+                                "Invalid cell {cell:?} at line {}, column {}",
+                                y + 1,
+                                x + 1
+                            )
+                        })
+                    })
+                    .collect::<anyhow::Result<Vec<T>>>()
+            })
+            .collect::<anyhow::Result<Vec<Vec<T>>>>()?;
+
+        Self::from_rows(rows, ragged)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Markers(HashMap<char, Vec<Coord>>);
+
+impl Markers {
+    #[allow(dead_code)]
+    pub fn all(&self, marker: char) -> &[Coord] {
+        self.0.get(&marker).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    #[allow(dead_code)]
+    pub fn first(&self, marker: char) -> Option<Coord> {
+        self.all(marker).first().copied()
+    }
+
+    #[allow(dead_code)]
+    pub fn single(&self, marker: char) -> anyhow::Result<Coord> {
+        match self.all(marker) {
+            [coord] => Ok(*coord),
+            [] => Err(anyhow::anyhow!("Marker {marker:?} not found")),
+            coords => Err(anyhow::anyhow!(
+                "Marker {marker:?} found {} times",
+                coords.len()
+            )),
+        }
+    }
+}
+
+impl<T: Default> Matrix<T> {
+    fn from_rows(mut rows: Vec<Vec<T>>, ragged: RaggedRows) -> anyhow::Result<Matrix<T>> {
+        let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+        if width == 0 {
+            anyhow::bail!("Empty matrix");
+        }
+
+        if ragged == RaggedRows::Reject {
+            if let Some((y, row)) = rows.iter().enumerate().find(|(_, row)| row.len() != width) {
+                anyhow::bail!("Line {} has {} cells, expected {width}", y + 1, row.len());
+            }
+        }
+
+        let height = rows.len();
+        let mut data = Vec::with_capacity(width * height);
+        for row in rows.iter_mut() {
+            row.resize_with(width, T::default);
+            data.append(row);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn parse_with<F>(s: &str, parser: F) -> anyhow::Result<Matrix<T>>
+    where
+        F: FnMut(char, (usize, usize)) -> anyhow::Result<T>,
+    {
+        Self::parse_grid(s, &[], RaggedRows::Reject, parser).map(|(matrix, _)| matrix)
+    }
+
+    #[allow(dead_code)]
+    pub fn parse_with_markers<F>(
+        s: &str,
+        markers: &[char],
+        parser: F,
+    ) -> anyhow::Result<(Matrix<T>, Markers)>
+    where
+        F: FnMut(char, (usize, usize)) -> anyhow::Result<T>,
+    {
+        Self::parse_grid(s, markers, RaggedRows::Reject, parser)
+    }
+
+    #[allow(dead_code)]
+    pub fn parse_chars(s: &str) -> anyhow::Result<Matrix<T>>
+    where
+        T: TryFrom<char>,
+    {
+        Self::parse_with(s, |ch, (x, y)| {
+            T::try_from(ch).map_err(|_| {
+                anyhow::anyhow!("Invalid cell {ch:?} at line {}, column {}", y + 1, x + 1)
+            })
+        })
+    }
+
+    pub fn parse_grid<F>(
+        s: &str,
+        markers: &[char],
+        ragged: RaggedRows,
+        mut parser: F,
+    ) -> anyhow::Result<(Matrix<T>, Markers)>
+    where
+        F: FnMut(char, (usize, usize)) -> anyhow::Result<T>,
+    {
+        let mut found = Markers::default();
+        let rows = s
+            .lines()
+            .enumerate()
+            .map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .map(|(x, ch)| {
+                        if markers.contains(&ch) {
+                            let coord = Coord::try_new(x, y)
+                                .ok_or_else(|| anyhow::anyhow!("Marker {ch:?} out of range"))?;
+                            found.0.entry(ch).or_default().push(coord);
+                        }
+
+                        parser(ch, (x, y))
+                    })
+                    .collect::<anyhow::Result<Vec<T>>>()
+            })
+            .collect::<anyhow::Result<Vec<Vec<T>>>>()?;
+
+        Ok((Self::from_rows(rows, ragged)?, found))
+    }
+}
+
+impl<T> Matrix<T> {
+    fn successors<C>(&self, offsets: &[(i32, i32)], coord: Coord, can_move: &mut C) -> Vec<Coord>
+    where
+        C: FnMut(&T, &T) -> bool,
+    {
+        let Some(from) = self.get_at(coord) else {
+            return vec![];
+        };
+
+        self.neighbours_iter(offsets, coord.x(), coord.y())
+            .filter(|(to, _)| can_move(from, to))
+            .map(|(_, neighbour)| neighbour)
+            .collect()
+    }
+
+    fn weighted_successors<C>(
+        &self,
+        offsets: &[(i32, i32)],
+        coord: Coord,
+        cost: &mut C,
+    ) -> Vec<(Coord, usize)>
+    where
+        C: FnMut(&T, &T) -> Option<usize>,
+    {
+        let Some(from) = self.get_at(coord) else {
+            return vec![];
+        };
+
+        self.neighbours_iter(offsets, coord.x(), coord.y())
+            .filter_map(|(to, neighbour)| cost(from, to).map(|cost| (neighbour, cost)))
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn bfs<C, G>(
+        &self,
+        offsets: &[(i32, i32)],
+        start: Coord,
+        mut can_move: C,
+        mut goal: G,
+    ) -> Option<Vec<Coord>>
+    where
+        C: FnMut(&T, &T) -> bool,
+        G: FnMut(Coord, &T) -> bool,
+    {
+        self.get_at(start)?;
+
+        pathfinding::prelude::bfs(
+            &start,
+            |&coord| self.successors(offsets, coord, &mut can_move),
+            |&coord| self.get_at(coord).is_some_and(|value| goal(coord, value)),
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn dijkstra<C, G>(
+        &self,
+        offsets: &[(i32, i32)],
+        start: Coord,
+        mut cost: C,
+        mut goal: G,
+    ) -> Option<(Vec<Coord>, usize)>
+    where
+        C: FnMut(&T, &T) -> Option<usize>,
+        G: FnMut(Coord, &T) -> bool,
+    {
+        self.get_at(start)?;
+
+        pathfinding::prelude::dijkstra(
+            &start,
+            |&coord| self.weighted_successors(offsets, coord, &mut cost),
+            |&coord| self.get_at(coord).is_some_and(|value| goal(coord, value)),
+        )
+    }
+
+    /// Manhattan distance is only admissible with `MATRIX_NEIGHBOURS_4` and step costs of at least 1.
+    #[allow(dead_code)]
+    pub fn astar<C>(
+        &self,
+        offsets: &[(i32, i32)],
+        start: Coord,
+        end: Coord,
+        mut cost: C,
+    ) -> Option<(Vec<Coord>, usize)>
+    where
+        C: FnMut(&T, &T) -> Option<usize>,
+    {
+        self.get_at(start)?;
+        self.get_at(end)?;
+
+        pathfinding::prelude::astar(
+            &start,
+            |&coord| self.weighted_successors(offsets, coord, &mut cost),
+            |coord| (coord.x().abs_diff(end.x()) + coord.y().abs_diff(end.y())) as usize,
+            |&coord| coord == end,
+        )
+    }
+
+    #[allow(dead_code)]
+    pub fn flood_fill<C>(&self, offsets: &[(i32, i32)], start: Coord, mut can_move: C) -> Vec<Coord>
+    where
+        C: FnMut(&T, &T) -> bool,
+    {
+        if self.get_at(start).is_none() {
+            return vec![];
+        }
+
+        pathfinding::prelude::bfs_reach(start, |&coord| {
+            self.successors(offsets, coord, &mut can_move)
+        })
+        .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn connected_components<C>(
+        &self,
+        offsets: &[(i32, i32)],
+        mut connected: C,
+    ) -> Vec<Vec<Coord>>
+    where
+        C: FnMut(&T, &T) -> bool,
+    {
+        let mut visited = vec![false; self.data.len()];
+        let mut components = vec![];
+
+        for index in 0..self.data.len() {
+            if visited[index] {
+                continue;
+            }
+
+            let Some(start) = self.coord_of(index) else {
+                continue;
+            };
+
+            let component = self.flood_fill(offsets, start, &mut connected);
+            for coord in component.iter() {
+                if let Some(index) = self.get_index(*coord) {
+                    visited[index] = true;
+                }
+            }
+
+            components.push(component);
+        }
+
+        components
+    }
+
+    #[allow(dead_code)]
+    pub fn distance_map<C>(
+        &self,
+        offsets: &[(i32, i32)],
+        start: Coord,
+        mut can_move: C,
+    ) -> Matrix<Option<usize>>
+    where
+        C: FnMut(&T, &T) -> bool,
+    {
+        let mut distances = Matrix::new(self.width, self.height);
+        if !self.contains(start) {
+            return distances;
+        }
+
+        let mut queue = std::collections::VecDeque::from([(start, 0)]);
+        distances.set_at(start, Some(0));
+
+        while let Some((coord, distance)) = queue.pop_front() {
+            for neighbour in self.successors(offsets, coord, &mut can_move) {
+                if let Some(cell @ None) = distances.get_at_mut(neighbour) {
+                    *cell = Some(distance + 1);
+                    queue.push_back((neighbour, distance + 1));
+                }
+            }
+        }
+
+        distances
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    #[allow(dead_code)]
+    pub fn lerp(from: Rgb, to: Rgb, t: f64) -> Rgb {
+        let t = t.clamp(0., 1.);
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+
+        Rgb(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+    }
+}
+
+impl Display for Rgb {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct RenderOptions {
+    pub ansi: bool,
+    pub rulers: bool,
+    pub highlight: HashSet<Coord>,
+    pub highlight_glyph: Option<char>,
+}
+
+impl<T> Matrix<T> {
+    #[allow(dead_code)]
+    pub fn render<G, C>(&self, options: &RenderOptions, glyph: G, colour: C) -> String
+    where
+        G: Fn(&T) -> char,
+        C: Fn(&T) -> Option<Rgb>,
+    {
+        let label_width = self.height.saturating_sub(1).to_string().len();
+        let mut output = String::new();
+
+        if options.rulers {
+            let digits = self.width.saturating_sub(1).to_string().len();
+            for digit in (0..digits).rev() {
+                output.push_str(&" ".repeat(label_width + 1));
+                for x in 0..self.width {
+                    let value = x / 10usize.pow(digit as u32);
+                    if value > 0 || digit == 0 {
+                        output.push(char::from(b'0' + (value % 10) as u8));
+                    } else {
+                        output.push(' ');
+                    }
+                }
+                output.push('\n');
+            }
+        }
+
+        for (y, row) in self.rows().enumerate() {
+            if options.rulers {
+                output.push_str(&format!("{y:>label_width$} "));
+            }
+
+            for (x, value) in row.iter().enumerate() {
+                let highlighted =
+                    Coord::try_new(x, y).is_some_and(|coord| options.highlight.contains(&coord));
+
+                let ch = match options.highlight_glyph {
+                    Some(ch) if highlighted => ch,
+                    _ => glyph(value),
+                };
+
+                if !options.ansi {
+                    output.push(ch);
+                    continue;
+                }
+
+                if highlighted {
+                    output.push_str("\x1b[7m");
+                }
+                if let Some(Rgb(r, g, b)) = colour(value) {
+                    output.push_str(&format!("\x1b[38;2;{r};{g};{b}m"));
+                }
+                output.push(ch);
+                output.push_str("\x1b[0m");
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    fn pixels<C>(&self, scale: usize, colour: C) -> Vec<u8>
+    where
+        C: Fn(&T) -> Rgb,
+    {
+        let mut pixels = Vec::with_capacity(self.size() * scale * scale * 3);
+        for row in self.rows() {
+            let line = row
+                .iter()
+                .flat_map(|value| {
+                    let Rgb(r, g, b) = colour(value);
+                    [r, g, b].repeat(scale)
+                })
+                .collect::<Vec<_>>();
+
+            for _ in 0..scale {
+                pixels.extend_from_slice(&line);
+            }
+        }
+
+        pixels
+    }
+
+    #[allow(dead_code)]
+    pub fn write_ppm<W, C>(&self, mut writer: W, scale: usize, colour: C) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+        C: Fn(&T) -> Rgb,
+    {
+        write!(
+            writer,
+            "P6\n{} {}\n255\n",
+            self.width * scale,
+            self.height * scale
+        )?;
+        writer.write_all(&self.pixels(scale, colour))
+    }
+
+    #[allow(dead_code)]
+    pub fn write_png<W, C>(&self, writer: W, scale: usize, colour: C) -> anyhow::Result<()>
+    where
+        W: std::io::Write,
+        C: Fn(&T) -> Rgb,
+    {
+        let mut encoder = png::Encoder::new(
+            writer,
+            (self.width * scale).try_into()?,
+            (self.height * scale).try_into()?,
+        );
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels(scale, colour))?;
+
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn write_svg<W, C>(&self, mut writer: W, scale: usize, colour: C) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+        C: Fn(&T) -> Rgb,
+    {
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#,
+            self.width * scale,
+            self.height * scale,
+            self.width,
+            self.height
+        )?;
+
+        for (y, row) in self.rows().enumerate() {
+            let colours = row.iter().map(&colour).collect::<Vec<_>>();
+            let mut x = 0;
+            while x < colours.len() {
+                let run = colours[x..]
+                    .iter()
+                    .take_while(|&&c| c == colours[x])
+                    .count();
+                writeln!(
+                    writer,
+                    r#"<rect x="{x}" y="{y}" width="{run}" height="1" fill="{}"/>"#,
+                    colours[x]
+                )?;
+                x += run;
+            }
+        }
+
+        writeln!(writer, "</svg>")
+    }
+
+    #[allow(dead_code)]
+    pub fn save_image<P, C>(&self, path: P, scale: usize, colour: C) -> anyhow::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+        C: Fn(&T) -> Rgb,
+    {
+        let path = path.as_ref();
+        let writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ppm") => self.write_ppm(writer, scale, colour)?,
+            Some("png") => self.write_png(writer, scale, colour)?,
+            Some("svg") => self.write_svg(writer, scale, colour)?,
+            _ => anyhow::bail!("Unsupported image format {}", path.display()),
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Serialize> Serialize for Matrix<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Matrix", 3)?;
+        state.serialize_field("width", &self.width)?;
+        state.serialize_field("height", &self.height)?;
+        state.serialize_field("data", &self.data)?;
+        state.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Matrix<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MatrixVisitor<T>(std::marker::PhantomData<T>);
+
+        impl<T> MatrixVisitor<T> {
+            fn build<E: de::Error>(
+                width: usize,
+                height: usize,
+                data: Vec<T>,
+            ) -> Result<Matrix<T>, E> {
+                if width.checked_mul(height) != Some(data.len()) {
+                    return Err(E::custom(format!(
+                        "{} cells do not fill a {width}x{height} matrix",
+                        data.len()
+                    )));
+                }
+
+                Ok(Matrix {
+                    width,
+                    height,
+                    data,
+                })
+            }
+        }
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for MatrixVisitor<T> {
+            type Value = Matrix<T>;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                write!(f, "a matrix with width, height and data")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let width = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let height = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let data = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+
+                Self::build(width, height, data)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let (mut width, mut height, mut data) = (None, None, None);
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "width" => width = Some(map.next_value()?),
+                        "height" => height = Some(map.next_value()?),
+                        "data" => data = Some(map.next_value()?),
+                        _ => {
+                            map.next_value::<de::IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                Self::build(
+                    width.ok_or_else(|| de::Error::missing_field("width"))?,
+                    height.ok_or_else(|| de::Error::missing_field("height"))?,
+                    data.ok_or_else(|| de::Error::missing_field("data"))?,
+                )
+            }
+        }
+
+        deserializer.deserialize_struct(
+            "Matrix",
+            &["width", "height", "data"],
+            MatrixVisitor(std::marker::PhantomData),
+        )
+    }
+}
+
+const BINARY_MAGIC: &[u8; 4] = b"MTRX";
+
+/// Fixed-size cell types that can be stored in the binary matrix format.
+pub trait BinaryCell: Sized {
+    const TAG: u8;
+    const SIZE: usize;
+
+    fn write_le(&self, out: &mut Vec<u8>);
+
+    fn read_le(bytes: &[u8]) -> anyhow::Result<Self>;
+}
+
+macro_rules! binary_cell {
+    ($($ty:ty => $tag:expr),* $(,)?) => {
+        $(
+            impl BinaryCell for $ty {
+                const TAG: u8 = $tag;
+                const SIZE: usize = std::mem::size_of::<$ty>();
+
+                fn write_le(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn read_le(bytes: &[u8]) -> anyhow::Result<Self> {
+                    Ok(<$ty>::from_le_bytes(bytes.try_into()?))
+                }
+            }
+        )*
+    };
+}
+
+binary_cell!(
+    u8 => 1, u16 => 2, u32 => 3, u64 => 4,
+    i8 => 5, i16 => 6, i32 => 7, i64 => 8,
+);
+
+impl BinaryCell for bool {
+    const TAG: u8 = 9;
+    const SIZE: usize = 1;
+
+    fn write_le(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn read_le(bytes: &[u8]) -> anyhow::Result<Self> {
+        match bytes {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => anyhow::bail!("Invalid bool cell {bytes:?}"),
+        }
+    }
+}
+
+impl BinaryCell for char {
+    const TAG: u8 = 10;
+    const SIZE: usize = 4;
+
+    fn write_le(&self, out: &mut Vec<u8>) {
+        (*self as u32).write_le(out);
+    }
+
+    fn read_le(bytes: &[u8]) -> anyhow::Result<Self> {
+        let code = u32::read_le(bytes)?;
+        char::from_u32(code).ok_or_else(|| anyhow::anyhow!("Invalid char cell {code:#x}"))
+    }
+}
+
+impl<T: BinaryCell> Matrix<T> {
+    /// Encodes as `MTRX`, a type tag, little-endian `u64` width and height, then the cells.
+    #[allow(dead_code)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(21 + self.data.len() * T::SIZE);
+        out.extend_from_slice(BINARY_MAGIC);
+        out.push(T::TAG);
+        out.extend_from_slice(&(self.width as u64).to_le_bytes());
+        out.extend_from_slice(&(self.height as u64).to_le_bytes());
+        for value in &self.data {
+            value.write_le(&mut out);
+        }
+
+        out
+    }
+
+    #[allow(dead_code)]
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Matrix<T>> {
+        let (header, cells) = bytes
+            .split_at_checked(21)
+            .ok_or_else(|| anyhow::anyhow!("Truncated matrix header"))?;
+
+        if &header[..4] != BINARY_MAGIC {
+            anyhow::bail!("Not a binary matrix");
+        }
+        if header[4] != T::TAG {
+            anyhow::bail!("Element type tag {} does not match {}", header[4], T::TAG);
+        }
+
+        let width = usize::try_from(u64::from_le_bytes(header[5..13].try_into()?))?;
+        let height = usize::try_from(u64::from_le_bytes(header[13..21].try_into()?))?;
+        let expected = width
+            .checked_mul(height)
+            .and_then(|size| size.checked_mul(T::SIZE))
+            .ok_or_else(|| anyhow::anyhow!("Matrix {width}x{height} is too large"))?;
+        if cells.len() != expected {
+            anyhow::bail!(
+                "Expected {expected} bytes of cells for {width}x{height}, got {}",
+                cells.len()
+            );
+        }
+
+        Ok(Self {
+            width,
+            height,
+            data: cells
+                .chunks_exact(T::SIZE)
+                .map(T::read_le)
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn save_binary<P: AsRef<std::path::Path>>(&self, path: P) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn load_binary<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Matrix<T>> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> Matrix<T> {
+    /// Parallel counterpart of `iter`, yielding cells in the same order.
+    #[allow(dead_code)]
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = (&T, (usize, usize))> {
+        let width = self.width;
+        self.data
+            .par_iter()
+            .enumerate()
+            .map(move |(index, value)| (value, (index % width, index / width)))
+    }
+
+    #[allow(dead_code)]
+    pub fn par_iter_mut(
+        &mut self,
+    ) -> impl IndexedParallelIterator<Item = (&mut T, (usize, usize))> {
+        let width = self.width;
+        self.data
+            .par_iter_mut()
+            .enumerate()
+            .map(move |(index, value)| (value, (index % width, index / width)))
+    }
+
+    #[allow(dead_code)]
+    pub fn par_map<U, F>(&self, f: F) -> Matrix<U>
+    where
+        U: Send,
+        F: Fn(&T, (usize, usize)) -> U + Sync + Send,
+    {
+        Matrix {
+            width: self.width,
+            height: self.height,
+            data: self.par_iter().map(|(value, xy)| f(value, xy)).collect(),
+        }
+    }
+
+    /// Builds a matrix from `f(x, y)`, handing out one row per task.
+    #[allow(dead_code)]
+    pub fn par_from_fn<F>(width: usize, height: usize, f: F) -> Matrix<T>
+    where
+        F: Fn(usize, usize) -> T + Sync + Send,
+    {
+        let data = (0..height)
+            .into_par_iter()
+            .flat_map_iter(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SparseGrid<T> {
+    cells: HashMap<Coord, T>,
+    bounds: Option<(Coord, Coord)>,
+}
+
+impl<T> Default for SparseGrid<T> {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::new(),
+            bounds: None,
+        }
+    }
+}
+
+impl<T> SparseGrid<T> {
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[allow(dead_code)]
+    pub fn from_matrix<F>(matrix: Matrix<T>, keep: F) -> Self
+    where
+        F: Fn(&T) -> bool,
+    {
+        let width = matrix.width;
+        matrix
+            .data
+            .into_iter()
+            .enumerate()
+            .filter(|(_, value)| keep(value))
+            .filter_map(|(index, value)| {
+                Coord::try_new(index % width, index / width).map(|coord| (coord, value))
+            })
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    #[allow(dead_code)]
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.bounds = None;
+    }
+
+    #[allow(dead_code)]
+    pub fn bounds(&self) -> Option<(Coord, Coord)> {
+        self.bounds
+    }
+
+    /// Number of columns spanned by the cells, saturating at `usize::MAX`.
+    #[allow(dead_code)]
+    pub fn width(&self) -> usize {
+        self.bounds
+            .map_or(0, |(min, max)| Self::span(min.x(), max.x()))
+    }
+
+    /// Number of rows spanned by the cells, saturating at `usize::MAX`.
+    #[allow(dead_code)]
+    pub fn height(&self) -> usize {
+        self.bounds
+            .map_or(0, |(min, max)| Self::span(min.y(), max.y()))
+    }
+
+    fn span(min: i64, max: i64) -> usize {
+        usize::try_from(max.abs_diff(min))
+            .ok()
+            .and_then(|span| span.checked_add(1))
+            .unwrap_or(usize::MAX)
+    }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, coord: Coord) -> bool {
+        self.cells.contains_key(&coord)
+    }
+
+    #[allow(dead_code)]
+    pub fn get<P>(&self, x: P, y: P) -> Option<&T>
+    where
+        P: TryInto<i64>,
+    {
+        Coord::try_new(x, y).and_then(|coord| self.get_at(coord))
+    }
+
+    #[allow(dead_code)]
+    pub fn get_at(&self, coord: Coord) -> Option<&T> {
+        self.cells.get(&coord)
+    }
+
+    #[allow(dead_code)]
+    pub fn get_mut<P>(&mut self, x: P, y: P) -> Option<&mut T>
+    where
+        P: TryInto<i64>,
+    {
+        Coord::try_new(x, y).and_then(|coord| self.get_at_mut(coord))
+    }
+
+    #[allow(dead_code)]
+    pub fn get_at_mut(&mut self, coord: Coord) -> Option<&mut T> {
+        self.cells.get_mut(&coord)
+    }
+
+    #[allow(dead_code)]
+    pub fn set<P>(&mut self, x: P, y: P, value: T) -> Option<T>
+    where
+        P: TryInto<i64>,
+    {
+        Coord::try_new(x, y).and_then(|coord| self.set_at(coord, value))
+    }
+
+    #[allow(dead_code)]
+    pub fn set_at(&mut self, coord: Coord, value: T) -> Option<T> {
+        self.bounds = Some(match self.bounds {
+            None => (coord, coord),
+            Some((min, max)) => (
+                Coord::xy(min.x().min(coord.x()), min.y().min(coord.y())),
+                Coord::xy(max.x().max(coord.x()), max.y().max(coord.y())),
+            ),
+        });
+
+        self.cells.insert(coord, value)
+    }
+
+    #[allow(dead_code)]
+    pub fn remove_at(&mut self, coord: Coord) -> Option<T> {
+        let value = self.cells.remove(&coord)?;
+
+        if let Some((min, max)) = self.bounds {
+            if coord.x() == min.x()
+                || coord.y() == min.y()
+                || coord.x() == max.x()
+                || coord.y() == max.y()
+            {
+                self.bounds = self.cells.keys().fold(None, |bounds, &coord| {
+                    let (min, max) = bounds.unwrap_or((coord, coord));
+                    Some((
+                        Coord::xy(min.x().min(coord.x()), min.y().min(coord.y())),
+                        Coord::xy(max.x().max(coord.x()), max.y().max(coord.y())),
+                    ))
+                });
+            }
+        }
+
+        Some(value)
+    }
+
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = (&T, Coord)> {
+        self.cells.iter().map(|(&coord, value)| (value, coord))
+    }
+
+    #[allow(dead_code)]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&mut T, Coord)> {
+        self.cells.iter_mut().map(|(&coord, value)| (value, coord))
+    }
+
+    #[allow(dead_code)]
+    pub fn coords(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.cells.keys().copied()
+    }
+
+    #[allow(dead_code)]
+    pub fn neighbours4<P>(&self, x: P, y: P) -> Vec<&T>
+    where
+        P: TryInto<i64>,
+    {
+        self.neighbours_iter(&MATRIX_NEIGHBOURS_4, x, y)
+            .map(|(value, _)| value)
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn neighbours8<P>(&self, x: P, y: P) -> Vec<&T>
+    where
+        P: TryInto<i64>,
+    {
+        self.neighbours_iter(&MATRIX_NEIGHBOURS_8, x, y)
+            .map(|(value, _)| value)
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn neighbours_iter<'a, P>(
+        &'a self,
+        offsets: &'a [(i32, i32)],
+        x: P,
+        y: P,
+    ) -> impl Iterator<Item = (&'a T, Coord)> + 'a
+    where
+        P: TryInto<i64>,
+    {
+        let coord = Coord::try_new(x, y);
+
+        offsets.iter().filter_map(move |&offset| {
+            let neighbour = coord?.checked_offset(offset)?;
+            self.get_at(neighbour).map(|value| (value, neighbour))
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn neighbours8_iter<P>(&self, x: P, y: P) -> impl Iterator<Item = (&T, Coord)>
+    where
+        P: TryInto<i64>,
+    {
+        self.neighbours_iter(&MATRIX_NEIGHBOURS_8, x, y)
+    }
+
+    #[allow(dead_code)]
+    pub fn neighbours4_iter<P>(&self, x: P, y: P) -> impl Iterator<Item = (&T, Coord)>
+    where
+        P: TryInto<i64>,
+    {
+        self.neighbours_iter(&MATRIX_NEIGHBOURS_4, x, y)
+    }
+
+    #[allow(dead_code)]
+    pub fn render_to_string<F>(&self, renderer: F) -> String
+    where
+        F: Fn(Option<&T>) -> String,
+    {
+        let Some((min, max)) = self.bounds else {
+            return String::new();
+        };
+
+        (min.y()..=max.y())
+            .map(|y| {
+                (min.x()..=max.x())
+                    .map(|x| renderer(self.get(x, y)))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[allow(dead_code)]
+    pub fn to_matrix(&self) -> Option<(Matrix<T>, Coord)>
+    where
+        T: Default + Clone,
+    {
+        let (min, _) = self.bounds?;
+        let mut matrix = Matrix::new(self.width(), self.height());
+
+        for (value, coord) in self.iter() {
+            matrix.set(coord.x() - min.x(), coord.y() - min.y(), value.clone());
+        }
+
+        Some((matrix, min))
+    }
+}
+
+impl<T> FromIterator<(Coord, T)> for SparseGrid<T> {
+    fn from_iter<I: IntoIterator<Item = (Coord, T)>>(iter: I) -> Self {
+        let mut grid = Self::new();
+        for (coord, value) in iter {
+            grid.set_at(coord, value);
+        }
+
+        grid
+    }
+}
+
+impl<T> Extend<(Coord, T)> for SparseGrid<T> {
+    fn extend<I: IntoIterator<Item = (Coord, T)>>(&mut self, iter: I) {
+        for (coord, value) in iter {
+            self.set_at(coord, value);
+        }
+    }
+}
+
+impl<T> From<Matrix<T>> for SparseGrid<T> {
+    fn from(matrix: Matrix<T>) -> Self {
+        Self::from_matrix(matrix, |_| true)
+    }
+}
+
+impl<T: Debug> Debug for SparseGrid<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "SparseGrid({}x{})", self.width(), self.height())?;
+        writeln!(
+            f,
+            "{}",
+            self.render_to_string(|value| value.map_or(" ".to_string(), |v| format!("{:?}", v)))
+        )
+    }
+}
+
+impl<T: Display> Display for SparseGrid<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{}",
+            self.render_to_string(|value| value.map_or(" ".to_string(), |v| v.to_string()))
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_matrix_out_of_range() {
+        let matrix = Matrix::<u8>::from("12\n34").unwrap();
+
+        assert_eq!(matrix.get(1, 1), Some(&4));
+        assert_eq!(matrix.get(-1, 0), None);
+        assert_eq!(matrix.get(usize::MAX, 0), None);
+        assert_eq!(matrix.get(i64::MAX, i64::MAX), None);
+        assert_eq!(matrix.get_at(Coord::xy(i64::MIN, 0)), None);
+        assert_eq!(
+            matrix
+                .neighbours4_iter(i64::MAX, 0)
+                .map(|(v, _)| *v)
+                .collect::<Vec<_>>(),
+            Vec::<u8>::new()
+        );
+        assert_eq!(
+            matrix
+                .neighbours4_iter(0usize, 0)
+                .map(|(_, coord)| coord)
+                .collect::<Vec<_>>(),
+            vec![Coord::xy(1, 0), Coord::xy(0, 1)]
+        );
+    }
+
+    #[test]
+    fn test_matrix_parse_errors() {
+        let error = Matrix::<u8>::from("12\n3x").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid cell \"x\" at line 2, column 2");
+
+        let error = Matrix::<u8>::from("123\n34").unwrap_err();
+        assert_eq!(error.to_string(), "Line 2 has 2 cells, expected 3");
+
+        let matrix = Matrix::<u8>::from_with("123\n34", RaggedRows::Pad).unwrap();
+        assert_eq!(matrix.data, vec![1, 2, 3, 3, 4, 0]);
+
+        let matrix = Matrix::<u32>::from_separated("10,20\n30, 40", ",").unwrap();
+        assert_eq!((matrix.width, matrix.height), (2, 2));
+        assert_eq!(matrix.get(1, 1), Some(&40));
+
+        assert!(Matrix::<u8>::from("").is_err());
+    }
+
+    #[test]
+    fn test_matrix_parse_with_markers() {
+        let (matrix, markers) =
+            Matrix::<u8>::parse_with_markers("Sab\ncdE", &['S', 'E'], |ch, _| {
+                Ok(match ch {
+                    'S' => b'a',
+                    'E' => b'z',
+                    ch => ch as u8,
+                })
+            })
+            .unwrap();
+
+        assert_eq!(matrix.get(0, 0), Some(&b'a'));
+        assert_eq!(matrix.get(2, 1), Some(&b'z'));
+        assert_eq!(markers.single('S').unwrap(), Coord::xy(0, 0));
+        assert_eq!(markers.single('E').unwrap(), Coord::xy(2, 1));
+        assert!(markers.single('X').is_err());
+
+        let error = Matrix::<u8>::parse_with("ab\nc!", |ch, (x, y)| match ch {
+            'a'..='z' => Ok(ch as u8),
+            _ => Err(anyhow::anyhow!("Bad {ch:?} at ({x},{y})")),
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), "Bad '!' at (1,1)");
+    }
+
+    #[test]
+    fn test_matrix_rows_columns_views() {
+        let matrix = Matrix::<u8>::from("123\n456\n789").unwrap();
+
+        assert_eq!(
+            matrix.rows().collect::<Vec<_>>(),
+            vec![[1, 2, 3], [4, 5, 6], [7, 8, 9]]
+        );
+        assert_eq!(
+            matrix.column(1).unwrap().collect::<Vec<_>>(),
+            vec![&2, &5, &8]
+        );
+        assert!(matrix.column(3).is_none());
+
+        let view = matrix.view(1, 1, 2, 2).unwrap();
+        assert_eq!(view.rows().collect::<Vec<_>>(), vec![[5, 6], [8, 9]]);
+        assert_eq!(view.get(1, 0), Some(&6));
+        assert_eq!(view.get(2, 0), None);
+        assert!(matrix.view(2, 2, 2, 1).is_none());
+    }
+
+    #[test]
+    fn test_matrix_rays() {
+        let matrix = Matrix::<u8>::from("123\n456\n789").unwrap();
+
+        let values = |ray: Ray<'_, u8>| ray.map(|(v, _)| *v).collect::<Vec<_>>();
+        assert_eq!(values(matrix.ray(0, 0, (1, 1))), vec![5, 9]);
+        assert_eq!(values(matrix.ray(1, 2, (0, -1))), vec![5, 2]);
+        assert_eq!(values(matrix.ray(2, 2, (1, 0))), Vec::<u8>::new());
+        assert_eq!(values(matrix.ray(0, 0, (0, 0))), Vec::<u8>::new());
+        assert_eq!(
+            matrix
+                .ray(0, 0, (1, 0))
+                .take_until(|&v| v >= 2)
+                .map(|(_, coord)| coord)
+                .collect::<Vec<_>>(),
+            vec![Coord::xy(1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_matrix_transforms() {
+        let matrix = Matrix::<u8>::from("123\n456").unwrap();
+        let rows = |matrix: &Matrix<u8>| matrix.rows().map(|row| row.to_vec()).collect::<Vec<_>>();
+
+        assert_eq!(
+            rows(&matrix.transposed()),
+            vec![vec![1, 4], vec![2, 5], vec![3, 6]]
+        );
+        assert_eq!(
+            rows(&matrix.rotated_cw()),
+            vec![vec![4, 1], vec![5, 2], vec![6, 3]]
+        );
+        assert_eq!(
+            rows(&matrix.rotated_ccw()),
+            vec![vec![3, 6], vec![2, 5], vec![1, 4]]
+        );
+        assert_eq!(
+            rows(&matrix.rotated_180()),
+            vec![vec![6, 5, 4], vec![3, 2, 1]]
+        );
+        assert_eq!(
+            rows(&matrix.flipped_horizontal()),
+            vec![vec![3, 2, 1], vec![6, 5, 4]]
+        );
+        assert_eq!(
+            rows(&matrix.flipped_vertical()),
+            vec![vec![4, 5, 6], vec![1, 2, 3]]
+        );
+
+        for (in_place, expected) in [
+            (
+                Matrix::transpose as fn(&mut Matrix<u8>),
+                matrix.transposed(),
+            ),
+            (Matrix::rotate_cw, matrix.rotated_cw()),
+            (Matrix::rotate_ccw, matrix.rotated_ccw()),
+            (Matrix::flip_vertical, matrix.flipped_vertical()),
+        ] {
+            let mut actual = matrix.clone();
+            in_place(&mut actual);
+            assert_eq!(
+                (actual.width, actual.height),
+                (expected.width, expected.height)
+            );
+            assert_eq!(actual.data, expected.data);
+        }
+
+        let padded = matrix.pad_sides(1, 0, 0, 2, 0);
+        assert_eq!(
+            rows(&padded),
+            vec![
+                vec![0, 0, 0, 0, 0],
+                vec![0, 0, 1, 2, 3],
+                vec![0, 0, 4, 5, 6]
+            ]
+        );
+        assert_eq!(rows(&padded.crop_to(|&v| v != 0).unwrap()), rows(&matrix));
+        assert_eq!(
+            rows(&matrix.crop(1, 0, 2, 2).unwrap()),
+            vec![vec![2, 3], vec![5, 6]]
+        );
+        assert!(matrix.crop(2, 0, 2, 1).is_none());
+    }
+
+    #[test]
+    fn test_sparse_grid() {
+        let mut grid = SparseGrid::new();
+        grid.set(-2, 1, '#');
+        grid.set(1, -1, '#');
+        grid.set(0, 0, 'o');
+
+        assert_eq!(grid.bounds(), Some((Coord::xy(-2, -1), Coord::xy(1, 1))));
+        assert_eq!((grid.width(), grid.height()), (4, 3));
+        assert_eq!(grid.neighbours8(0, 0), vec![&'#']);
+        assert_eq!(
+            grid.render_to_string(|v| v.unwrap_or(&'.').to_string()),
+            "...#\n..o.\n#..."
+        );
+
+        assert_eq!(grid.remove_at(Coord::xy(-2, 1)), Some('#'));
+        assert_eq!(grid.bounds(), Some((Coord::xy(0, -1), Coord::xy(1, 0))));
+
+        let (matrix, origin) = grid.to_matrix().unwrap();
+        assert_eq!(origin, Coord::xy(0, -1));
+        assert_eq!(
+            matrix.rows().collect::<Vec<_>>(),
+            vec![['\0', '#'], ['o', '\0']]
+        );
+
+        let grid = SparseGrid::from_matrix(matrix, |&v| v != '\0');
+        assert_eq!(grid.len(), 2);
+        assert_eq!(grid.get(0, 1), Some(&'o'));
+
+        let mut grid = SparseGrid::new();
+        grid.set(i64::MIN, 0, '#');
+        grid.set(i64::MAX, i64::MAX, '#');
+        assert_eq!(
+            (grid.width(), grid.height()),
+            (usize::MAX, i64::MAX as usize + 1)
+        );
+    }
+
+    #[test]
+    fn test_matrix_search() {
+        let matrix = Matrix::<char>::parse_with("..#.\n.##.\n....", |ch, _| Ok(ch)).unwrap();
+        let open = |_: &char, to: &char| *to == '.';
+        let start = Coord::xy(0, 0);
+        let end = Coord::xy(3, 0);
+
+        let path = matrix
+            .bfs(&MATRIX_NEIGHBOURS_4, start, open, |coord, _| coord == end)
+            .unwrap();
+        assert_eq!(path.len(), 8);
+        assert_eq!((path[0], path[7]), (start, end));
+
+        let cost = |_: &char, to: &char| (*to == '.').then_some(2);
+        let (dijkstra, dijkstra_cost) = matrix
+            .dijkstra(&MATRIX_NEIGHBOURS_4, start, cost, |coord, _| coord == end)
+            .unwrap();
+        let (astar, astar_cost) = matrix
+            .astar(&MATRIX_NEIGHBOURS_4, start, end, cost)
+            .unwrap();
+        assert_eq!((dijkstra.len(), dijkstra_cost), (8, 14));
+        assert_eq!((astar.len(), astar_cost), (8, 14));
+
+        let distances = matrix.distance_map(&MATRIX_NEIGHBOURS_4, start, open);
+        assert_eq!(distances.get_at(end), Some(&Some(7)));
+        assert_eq!(distances.get(2, 0), Some(&None));
+
+        assert_eq!(
+            matrix.flood_fill(&MATRIX_NEIGHBOURS_8, start, open).len(),
+            9
+        );
+        let components = matrix.connected_components(&MATRIX_NEIGHBOURS_4, |a, b| a == b);
+        assert_eq!(components.len(), 2);
+        assert_eq!(components.iter().map(Vec::len).sum::<usize>(), 12);
+    }
+
+    #[test]
+    fn test_matrix_render() {
+        let matrix = Matrix::<u8>::from("0123456789\n9876543210").unwrap();
+        let glyph = |v: &u8| char::from(b'0' + v);
+
+        let options = RenderOptions {
+            rulers: true,
+            highlight: HashSet::from([Coord::xy(1, 0), Coord::xy(2, 1)]),
+            highlight_glyph: Some('*'),
+            ..Default::default()
+        };
+        assert_eq!(
+            matrix.render(&options, glyph, |_| None),
+            "  0123456789\n0 0*23456789\n1 98*6543210\n"
+        );
+        assert_eq!(
+            Matrix::<u8>::new(11, 1).render(&options, glyph, |_| None),
+            "            1\n  01234567890\n0 0*000000000\n"
+        );
+
+        let options = RenderOptions {
+            ansi: true,
+            highlight: HashSet::from([Coord::xy(0, 0)]),
+            ..Default::default()
+        };
+        let small = matrix.crop(0, 0, 2, 1).unwrap();
+        assert_eq!(
+            small.render(&options, glyph, |&v| (v > 0).then_some(Rgb(255, 0, 0))),
+            "\x1b[7m0\x1b[0m\x1b[38;2;255;0;0m1\x1b[0m\n"
+        );
+
+        let colour = |&v: &u8| Rgb::lerp(Rgb(0, 0, 0), Rgb(255, 255, 255), v as f64 / 9.);
+        let mut ppm = vec![];
+        small.write_ppm(&mut ppm, 1, colour).unwrap();
+        assert_eq!(ppm, b"P6\n2 1\n255\n\x00\x00\x00\x1c\x1c\x1c".to_vec());
+
+        let mut png = vec![];
+        matrix.write_png(&mut png, 2, colour).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+
+        let mut svg = vec![];
+        Matrix::<u8>::from("11\n12")
+            .unwrap()
+            .write_svg(&mut svg, 4, colour)
+            .unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert!(svg.contains(r##"<rect x="0" y="0" width="2" height="1" fill="#1c1c1c"/>"##));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_matrix_parallel() {
+        let matrix = Matrix::par_from_fn(37, 23, |x, y| x * 100 + y);
+        assert_eq!(matrix.get(36, 22), Some(&3622));
+        assert!(matrix.iter().all(|(&value, (x, y))| value == x * 100 + y));
+
+        assert!(matrix.par_iter().collect::<Vec<_>>() == matrix.iter().collect::<Vec<_>>());
+
+        let doubled = matrix.par_map(|&value, (x, _)| value * 2 + x);
+        let mut expected = matrix.clone();
+        expected
+            .iter_mut()
+            .for_each(|(value, (x, _))| *value = *value * 2 + x);
+        assert!(doubled == expected);
+
+        let mut in_place = matrix.clone();
+        in_place
+            .par_iter_mut()
+            .for_each(|(value, (x, _))| *value = *value * 2 + x);
+        assert!(in_place == expected);
+
+        let empty = Matrix::<u8>::par_from_fn(5, 0, |_, _| 1);
+        assert_eq!(empty.size(), 0);
+    }
+
+    #[test]
+    fn test_matrix_serialization() {
+        let matrix = Matrix::<char>::parse_chars("#.o\n.#~").unwrap();
+
+        let json = serde_json::to_string(&matrix).unwrap();
+        assert_eq!(
+            json,
+            r##"{"width":3,"height":2,"data":["#",".","o",".","#","~"]}"##
+        );
+        assert!(serde_json::from_str::<Matrix<char>>(&json).unwrap() == matrix);
+        assert!(
+            serde_json::from_str::<Matrix<u8>>(r#"{"width":2,"height":2,"data":[1]}"#).is_err()
+        );
+        assert!(
+            serde_json::from_str::<Matrix<u8>>(r#"[1,1,[7]]"#).unwrap()
+                == Matrix::from("7").unwrap()
+        );
+
+        let bytes = matrix.to_bytes();
+        assert_eq!(&bytes[..5], b"MTRX\x0a");
+        assert_eq!(bytes.len(), 21 + 6 * 4);
+        assert!(Matrix::<char>::from_bytes(&bytes).unwrap() == matrix);
+        assert!(Matrix::<u32>::from_bytes(&bytes).is_err());
+        assert!(Matrix::<char>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let numbers = Matrix::<i64>::from("123\n456").unwrap();
+        assert!(Matrix::<i64>::from_bytes(&numbers.to_bytes()).unwrap() == numbers);
     }
 }